@@ -0,0 +1,73 @@
+//! Everything the crawler needs from the platform: finding the game window, capturing it and
+//! sending it clicks and keys. `DungeonCrawler` only talks to the game through `GameBackend`, so
+//! it can be driven by the real game on Windows or X11, or by saved screenshots anywhere.
+use std::{thread, time::Duration};
+
+use image::{imageops, RgbaImage};
+
+mod mock;
+#[cfg(windows)]
+mod windows;
+#[cfg(feature = "x11")]
+mod x11;
+
+pub use mock::MockBackend;
+#[cfg(windows)]
+pub use windows::WindowsBackend;
+#[cfg(feature = "x11")]
+pub use x11::X11Backend;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Key {
+    Backspace,
+    Enter,
+    Char(char),
+}
+
+#[derive(Debug)]
+pub enum BackendError {
+    ConnectionFailed,
+    WindowNotFound,
+    CaptureFailed,
+    OutOfBounds,
+}
+
+pub trait GameBackend {
+    /// Looks up the window with the given title and returns the screen position of its client
+    /// area. Every other method acts on the window found here.
+    fn find_window(&mut self, title: &str) -> Result<(i32, i32), BackendError>;
+
+    /// Captures the whole client area of the window
+    fn capture(&mut self) -> Result<RgbaImage, BackendError>;
+
+    /// Captures a `size` region at `offset` within the client area
+    fn capture_region(
+        &mut self,
+        offset: (u32, u32),
+        size: (u32, u32),
+    ) -> Result<RgbaImage, BackendError> {
+        let image = self.capture()?;
+        if offset.0 + size.0 > image.width() || offset.1 + size.1 > image.height() {
+            return Err(BackendError::OutOfBounds);
+        }
+        Ok(imageops::crop_imm(&image, offset.0, offset.1, size.0, size.1).to_image())
+    }
+
+    /// Moves the mouse to x,y in screen coordinates and clicks
+    fn click(&mut self, x: i32, y: i32, button: MouseButton);
+
+    fn key(&mut self, key: Key);
+
+    fn send_str(&mut self, text: &str);
+
+    /// Gives the game time to catch up with the input sent so far
+    fn wait(&mut self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
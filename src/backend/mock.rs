@@ -0,0 +1,70 @@
+use std::{collections::VecDeque, path::Path, time::Duration};
+
+use image::{ImageResult, RgbaImage};
+
+use super::{BackendError, GameBackend, Key, MouseButton};
+
+/// A backend that replays saved screenshots instead of capturing a window. Each capture serves
+/// the next frame, and fails once they have all been served. Input is recorded rather than
+/// sent, and waits return immediately.
+#[derive(Debug, Default)]
+pub struct MockBackend {
+    frames: VecDeque<RgbaImage>,
+    window_pos: (i32, i32),
+    pub clicks: Vec<(i32, i32, MouseButton)>,
+    pub keys: Vec<Key>,
+    pub text: String,
+}
+
+impl MockBackend {
+    /// Serves the frames in order, with the client area of the window at the top left of the
+    /// screen
+    pub fn new(frames: Vec<RgbaImage>) -> Self {
+        Self {
+            frames: frames.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Loads each frame from a screenshot of the whole client area
+    pub fn from_files<P: AsRef<Path>>(paths: &[P]) -> ImageResult<Self> {
+        let frames = paths
+            .iter()
+            .map(|path| Ok(image::open(path)?.to_rgba8()))
+            .collect::<ImageResult<Vec<RgbaImage>>>()?;
+        Ok(Self::new(frames))
+    }
+
+    pub fn with_window_pos(mut self, x: i32, y: i32) -> Self {
+        self.window_pos = (x, y);
+        self
+    }
+
+    pub fn frames_left(&self) -> usize {
+        self.frames.len()
+    }
+}
+
+impl GameBackend for MockBackend {
+    fn find_window(&mut self, _title: &str) -> Result<(i32, i32), BackendError> {
+        Ok(self.window_pos)
+    }
+
+    fn capture(&mut self) -> Result<RgbaImage, BackendError> {
+        self.frames.pop_front().ok_or(BackendError::CaptureFailed)
+    }
+
+    fn click(&mut self, x: i32, y: i32, button: MouseButton) {
+        self.clicks.push((x, y, button));
+    }
+
+    fn key(&mut self, key: Key) {
+        self.keys.push(key);
+    }
+
+    fn send_str(&mut self, text: &str) {
+        self.text.push_str(text);
+    }
+
+    fn wait(&mut self, _duration: Duration) {}
+}
@@ -0,0 +1,116 @@
+use std::{thread, time::Duration};
+
+use enigo::{Enigo, MouseControllable};
+use image::RgbaImage;
+use win_screenshot::prelude::*;
+use windows_sys::Win32::Foundation::RECT;
+use windows_sys::Win32::UI::WindowsAndMessaging::{GetWindowInfo, WINDOWINFO};
+
+use super::{BackendError, GameBackend, Key, MouseButton};
+
+// the game misses clicks sent right after the mouse moves
+const MOVE_DELAY: u64 = 15;
+
+/// Captures the window with BitBlt, moves the mouse with enigo and types with winput
+#[derive(Debug)]
+pub struct WindowsBackend {
+    hwnd: isize,
+    enigo: Enigo,
+}
+
+impl WindowsBackend {
+    pub fn new() -> Self {
+        Self {
+            hwnd: 0,
+            enigo: Enigo::new(),
+        }
+    }
+
+    fn capture_ex(
+        &self,
+        offset: Option<[i32; 2]>,
+        size: Option<[i32; 2]>,
+    ) -> Result<RgbaImage, BackendError> {
+        let buffer = capture_window_ex(self.hwnd, Using::BitBlt, Area::ClientOnly, offset, size)
+            .map_err(|_| BackendError::CaptureFailed)?;
+        RgbaImage::from_raw(buffer.width, buffer.height, buffer.pixels)
+            .ok_or(BackendError::CaptureFailed)
+    }
+}
+
+impl Default for WindowsBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GameBackend for WindowsBackend {
+    fn find_window(&mut self, title: &str) -> Result<(i32, i32), BackendError> {
+        self.hwnd = find_window(title).map_err(|_| BackendError::WindowNotFound)?;
+
+        let mut window_info = WINDOWINFO {
+            cbSize: 0,
+            rcWindow: RECT {
+                left: 0,
+                top: 0,
+                right: 0,
+                bottom: 0,
+            },
+            rcClient: RECT {
+                left: 0,
+                top: 0,
+                right: 0,
+                bottom: 0,
+            },
+            dwStyle: 0,
+            dwExStyle: 0,
+            dwWindowStatus: 0,
+            cxWindowBorders: 0,
+            cyWindowBorders: 0,
+            atomWindowType: 0,
+            wCreatorVersion: 0,
+        };
+
+        unsafe { GetWindowInfo(self.hwnd, &mut window_info) };
+
+        Ok((window_info.rcClient.left, window_info.rcClient.top))
+    }
+
+    fn capture(&mut self) -> Result<RgbaImage, BackendError> {
+        self.capture_ex(None, None)
+    }
+
+    fn capture_region(
+        &mut self,
+        offset: (u32, u32),
+        size: (u32, u32),
+    ) -> Result<RgbaImage, BackendError> {
+        self.capture_ex(
+            Some([offset.0 as i32, offset.1 as i32]),
+            Some([size.0 as i32, size.1 as i32]),
+        )
+    }
+
+    fn click(&mut self, x: i32, y: i32, button: MouseButton) {
+        self.enigo.mouse_move_to(x, y);
+        thread::sleep(Duration::from_millis(MOVE_DELAY));
+        self.enigo.mouse_click(match button {
+            MouseButton::Left => enigo::MouseButton::Left,
+            MouseButton::Right => enigo::MouseButton::Right,
+        });
+    }
+
+    fn key(&mut self, key: Key) {
+        use winput::Vk;
+
+        match key {
+            Key::Backspace => winput::send(Vk::Backspace),
+            Key::Enter => winput::send(Vk::Enter),
+            Key::Char(c) => winput::send(c),
+        }
+    }
+
+    fn send_str(&mut self, text: &str) {
+        winput::send_str(text);
+    }
+}
@@ -0,0 +1,302 @@
+//! The X11 backend, enabled by the `x11` feature. It can be tried out without the game on a
+//! headless server: start `Xvfb :99`, set `DISPLAY=:99` and open a stand-in window showing a
+//! saved screenshot with `X11Backend::open_stand_in`.
+use std::{thread, time::Duration};
+
+use image::{imageops, GenericImageView, RgbaImage};
+use x11rb::{
+    connection::Connection,
+    protocol::{
+        xproto::{
+            AtomEnum, ChangeWindowAttributesAux, ConnectionExt as _, CreateGCAux, CreateWindowAux,
+            ImageFormat, Keysym, PropMode, Window, WindowClass, BUTTON_PRESS_EVENT,
+            BUTTON_RELEASE_EVENT, KEY_PRESS_EVENT, KEY_RELEASE_EVENT, MOTION_NOTIFY_EVENT,
+        },
+        xtest::ConnectionExt as _,
+    },
+    rust_connection::RustConnection,
+    wrapper::ConnectionExt as _,
+    COPY_DEPTH_FROM_PARENT, CURRENT_TIME,
+};
+
+use super::{BackendError, GameBackend, Key, MouseButton};
+
+// the game misses clicks sent right after the mouse moves
+const MOVE_DELAY: u64 = 15;
+
+const XK_BACKSPACE: Keysym = 0xff08;
+const XK_RETURN: Keysym = 0xff0d;
+const XK_SHIFT_L: Keysym = 0xffe1;
+
+// rows of the stand-in image uploaded per request, keeping each under the core request size
+const UPLOAD_ROWS: u32 = 32;
+
+/// Captures the window with GetImage and sends input through the XTest extension. Works with
+/// the game running under Wine or Proton, whose windows are ordinary X11 windows.
+#[derive(Debug)]
+pub struct X11Backend {
+    conn: RustConnection,
+    screen: usize,
+    root: Window,
+    window: Window,
+    min_keycode: u8,
+    keysyms_per_keycode: usize,
+    keysyms: Vec<Keysym>,
+}
+
+impl X11Backend {
+    /// Connects to the display named by `$DISPLAY`
+    pub fn new() -> Result<Self, BackendError> {
+        let (conn, screen) = x11rb::connect(None).map_err(|_| BackendError::ConnectionFailed)?;
+        let root = conn.setup().roots[screen].root;
+
+        // keysyms for every keycode, used to find the key that types a given character
+        let min_keycode = conn.setup().min_keycode;
+        let count = conn.setup().max_keycode - min_keycode + 1;
+        let mapping = conn
+            .get_keyboard_mapping(min_keycode, count)
+            .map_err(|_| BackendError::ConnectionFailed)?
+            .reply()
+            .map_err(|_| BackendError::ConnectionFailed)?;
+
+        Ok(Self {
+            conn,
+            screen,
+            root,
+            window: root,
+            min_keycode,
+            keysyms_per_keycode: mapping.keysyms_per_keycode as usize,
+            keysyms: mapping.keysyms,
+        })
+    }
+
+    /// Opens a window titled `title` showing `image`, to stand in for the game. The image is set
+    /// as the window background so the server repaints it without an event loop.
+    pub fn open_stand_in(&self, title: &str, image: &RgbaImage) -> Result<Window, BackendError> {
+        self.stand_in(title, image)
+            .map_err(|_| BackendError::ConnectionFailed)
+    }
+
+    fn stand_in(
+        &self,
+        title: &str,
+        image: &RgbaImage,
+    ) -> Result<Window, Box<dyn std::error::Error>> {
+        let screen = &self.conn.setup().roots[self.screen];
+        let (width, height) = (image.width() as u16, image.height() as u16);
+
+        let window = self.conn.generate_id()?;
+        self.conn.create_window(
+            COPY_DEPTH_FROM_PARENT,
+            window,
+            self.root,
+            0,
+            0,
+            width,
+            height,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            screen.root_visual,
+            &CreateWindowAux::new(),
+        )?;
+
+        let pixmap = self.conn.generate_id()?;
+        self.conn
+            .create_pixmap(screen.root_depth, pixmap, window, width, height)?;
+        let gc = self.conn.generate_id()?;
+        self.conn.create_gc(gc, pixmap, &CreateGCAux::new())?;
+        for y in (0..image.height()).step_by(UPLOAD_ROWS as usize) {
+            let rows = imageops::crop_imm(image, 0, y, image.width(), UPLOAD_ROWS);
+            let data = rows
+                .pixels()
+                .flat_map(|(_, _, p)| [p.0[2], p.0[1], p.0[0], 0])
+                .collect::<Vec<u8>>();
+            self.conn.put_image(
+                ImageFormat::Z_PIXMAP,
+                pixmap,
+                gc,
+                width,
+                rows.height() as u16,
+                0,
+                y as i16,
+                0,
+                screen.root_depth,
+                &data,
+            )?;
+        }
+        self.conn.free_gc(gc)?;
+
+        self.conn.change_window_attributes(
+            window,
+            &ChangeWindowAttributesAux::new().background_pixmap(pixmap),
+        )?;
+        self.conn.free_pixmap(pixmap)?;
+        self.conn.change_property8(
+            PropMode::REPLACE,
+            window,
+            AtomEnum::WM_NAME,
+            AtomEnum::STRING,
+            title.as_bytes(),
+        )?;
+        self.conn.map_window(window)?;
+        self.conn.flush()?;
+        Ok(window)
+    }
+
+    fn window_name(&self, window: Window) -> Option<String> {
+        let net_wm_name = self.intern_atom(b"_NET_WM_NAME")?;
+        for property in [net_wm_name, AtomEnum::WM_NAME.into()] {
+            let reply = self
+                .conn
+                .get_property(false, window, property, AtomEnum::ANY, 0, 256)
+                .ok()?
+                .reply()
+                .ok()?;
+            if !reply.value.is_empty() {
+                return Some(String::from_utf8_lossy(&reply.value).into_owned());
+            }
+        }
+        None
+    }
+
+    fn intern_atom(&self, name: &[u8]) -> Option<u32> {
+        Some(self.conn.intern_atom(true, name).ok()?.reply().ok()?.atom)
+    }
+
+    // depth first search of the window tree, since window managers reparent the game's window
+    // into a frame of their own
+    fn find_child(&self, parent: Window, title: &str) -> Option<Window> {
+        if self.window_name(parent).as_deref() == Some(title) {
+            return Some(parent);
+        }
+        let tree = self.conn.query_tree(parent).ok()?.reply().ok()?;
+        tree.children
+            .iter()
+            .find_map(|&child| self.find_child(child, title))
+    }
+
+    // the keycode typing the keysym, and whether shift has to be held for it
+    fn keycode(&self, keysym: Keysym) -> Option<(u8, bool)> {
+        self.keysyms
+            .chunks(self.keysyms_per_keycode)
+            .enumerate()
+            .find_map(|(i, syms)| {
+                let level = syms.iter().take(2).position(|&sym| sym == keysym)?;
+                Some((self.min_keycode + i as u8, level == 1))
+            })
+    }
+
+    fn fake_input(&self, event: u8, detail: u8, x: i16, y: i16) {
+        // input is fire and forget, like enigo and winput on Windows
+        let _ = self
+            .conn
+            .xtest_fake_input(event, detail, CURRENT_TIME, self.root, x, y, 0);
+    }
+
+    fn press_keysym(&self, keysym: Keysym) {
+        let Some((keycode, shift)) = self.keycode(keysym) else {
+            return;
+        };
+        let shift = shift.then(|| self.keycode(XK_SHIFT_L)).flatten();
+        if let Some((shift, _)) = shift {
+            self.fake_input(KEY_PRESS_EVENT, shift, 0, 0);
+        }
+        self.fake_input(KEY_PRESS_EVENT, keycode, 0, 0);
+        self.fake_input(KEY_RELEASE_EVENT, keycode, 0, 0);
+        if let Some((shift, _)) = shift {
+            self.fake_input(KEY_RELEASE_EVENT, shift, 0, 0);
+        }
+        let _ = self.conn.flush();
+    }
+}
+
+impl GameBackend for X11Backend {
+    fn find_window(&mut self, title: &str) -> Result<(i32, i32), BackendError> {
+        self.window = self
+            .find_child(self.root, title)
+            .ok_or(BackendError::WindowNotFound)?;
+
+        let origin = self
+            .conn
+            .translate_coordinates(self.window, self.root, 0, 0)
+            .map_err(|_| BackendError::WindowNotFound)?
+            .reply()
+            .map_err(|_| BackendError::WindowNotFound)?;
+
+        Ok((origin.dst_x as i32, origin.dst_y as i32))
+    }
+
+    fn capture(&mut self) -> Result<RgbaImage, BackendError> {
+        let geometry = self
+            .conn
+            .get_geometry(self.window)
+            .map_err(|_| BackendError::CaptureFailed)?
+            .reply()
+            .map_err(|_| BackendError::CaptureFailed)?;
+        self.capture_region((0, 0), (geometry.width as u32, geometry.height as u32))
+    }
+
+    fn capture_region(
+        &mut self,
+        offset: (u32, u32),
+        size: (u32, u32),
+    ) -> Result<RgbaImage, BackendError> {
+        let reply = self
+            .conn
+            .get_image(
+                ImageFormat::Z_PIXMAP,
+                self.window,
+                offset.0 as i16,
+                offset.1 as i16,
+                size.0 as u16,
+                size.1 as u16,
+                !0,
+            )
+            .map_err(|_| BackendError::CaptureFailed)?
+            .reply()
+            .map_err(|_| BackendError::OutOfBounds)?;
+
+        // 24 and 32 bit visuals both come back as BGRX, four bytes per pixel
+        if reply.depth < 24 {
+            return Err(BackendError::CaptureFailed);
+        }
+        let pixels = reply
+            .data
+            .chunks_exact(4)
+            .flat_map(|bgrx| [bgrx[2], bgrx[1], bgrx[0], 255])
+            .collect::<Vec<u8>>();
+        RgbaImage::from_raw(size.0, size.1, pixels).ok_or(BackendError::CaptureFailed)
+    }
+
+    fn click(&mut self, x: i32, y: i32, button: MouseButton) {
+        let button = match button {
+            MouseButton::Left => 1,
+            MouseButton::Right => 3,
+        };
+        self.fake_input(MOTION_NOTIFY_EVENT, 0, x as i16, y as i16);
+        let _ = self.conn.flush();
+        thread::sleep(Duration::from_millis(MOVE_DELAY));
+        self.fake_input(BUTTON_PRESS_EVENT, button, 0, 0);
+        self.fake_input(BUTTON_RELEASE_EVENT, button, 0, 0);
+        let _ = self.conn.flush();
+    }
+
+    fn key(&mut self, key: Key) {
+        match key {
+            Key::Backspace => self.press_keysym(XK_BACKSPACE),
+            Key::Enter => self.press_keysym(XK_RETURN),
+            // Latin-1 keysyms are the same as the character codes
+            Key::Char(c) => self.press_keysym(c as Keysym),
+        }
+    }
+
+    fn send_str(&mut self, text: &str) {
+        for c in text.chars() {
+            match c {
+                '\n' | '\r' => self.key(Key::Enter),
+                '\x08' => self.key(Key::Backspace),
+                c => self.key(Key::Char(c)),
+            }
+        }
+    }
+}
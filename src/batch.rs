@@ -0,0 +1,193 @@
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use rayon::prelude::*;
+
+use crate::puzzle::{ParsePuzzleError, Puzzle};
+
+// lines are solved in chunks so a full seed dump never has to sit in memory
+const CHUNK_SIZE: usize = 100_000;
+
+/// The outcome of solving one puzzle from a corpus
+pub struct BatchResult {
+    pub seed: u32,
+    // the solved board as written by `Puzzle::board_string`, or why there is no solution
+    pub solution: Result<String, String>,
+    pub difficulty: Option<f32>,
+    pub time: Duration,
+}
+
+impl Display for BatchResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let micros = self.time.as_micros();
+        match (&self.solution, self.difficulty) {
+            (Ok(board), Some(difficulty)) => write!(
+                f,
+                "{:08} solved {difficulty:.1} {micros}us {board}",
+                self.seed
+            ),
+            (Ok(board), None) => write!(f, "{:08} solved - {micros}us {board}", self.seed),
+            (Err(reason), _) => write!(f, "{:08} unsolved - {micros}us {reason}", self.seed),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct BatchSummary {
+    pub solved: usize,
+    pub unsolved: usize,
+    // lines that couldn't be read as a puzzle, numbered from 1, and why
+    pub invalid: Vec<(usize, ParsePuzzleError)>,
+    pub time: Duration,
+}
+
+/// Solves every puzzle across all cores. Results are returned in the same order as the input.
+pub fn solve_all(puzzles: Vec<Puzzle>) -> Vec<BatchResult> {
+    puzzles.into_par_iter().map(solve_one).collect()
+}
+
+fn solve_one(mut puzzle: Puzzle) -> BatchResult {
+    let start = Instant::now();
+    let result = puzzle.solve();
+    let time = start.elapsed();
+
+    let seed = puzzle.get_seed().unwrap_or_default();
+    match result {
+        Ok(report) => BatchResult {
+            seed,
+            solution: Ok(puzzle.board_string()),
+            difficulty: Some(report.difficulty()),
+            time,
+        },
+        Err(contradiction) => BatchResult {
+            seed,
+            solution: Err(contradiction.to_string()),
+            difficulty: None,
+            time,
+        },
+    }
+}
+
+/// Reads a corpus of `Puzzle::serialize` lines, as printed by `read_loop`, and writes one result
+/// line per seed to `output`. Lines that can't be parsed are skipped and listed in the summary.
+/// Dumps written before the lines held the wall counts can't be solved, and have to be dumped
+/// again; each of their lines is listed as `ParsePuzzleError::MissingCounts`.
+pub fn solve_corpus(input: &Path, output: &Path) -> io::Result<BatchSummary> {
+    let reader = BufReader::new(File::open(input)?);
+    let mut writer = BufWriter::new(File::create(output)?);
+    let summary = solve_lines(reader, &mut writer, CHUNK_SIZE)?;
+    writer.flush()?;
+    Ok(summary)
+}
+
+fn solve_lines(
+    reader: impl BufRead,
+    mut writer: impl Write,
+    chunk_size: usize,
+) -> io::Result<BatchSummary> {
+    let start = Instant::now();
+    let mut summary = BatchSummary::default();
+
+    let mut lines = reader.lines().enumerate().peekable();
+    while lines.peek().is_some() {
+        let mut puzzles = Vec::with_capacity(chunk_size);
+        for (i, line) in lines.by_ref().take(chunk_size) {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match line.parse::<Puzzle>() {
+                Ok(puzzle) => puzzles.push(puzzle),
+                Err(reason) => summary.invalid.push((i + 1, reason)),
+            }
+        }
+
+        for result in solve_all(puzzles) {
+            if result.solution.is_ok() {
+                summary.solved += 1;
+            } else {
+                summary.unsolved += 1;
+            }
+            writeln!(writer, "{result}")?;
+        }
+    }
+
+    summary.time = start.elapsed();
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // seeded lines from the corpus, each with one solution
+    const SOLVABLE: [&str; 2] = [
+        "00000001 31524373 23332366                                 E   T          E  E     E       ",
+        "00000002 15444653 77623250 E                     E                     T        E  E       ",
+    ];
+    // the enemies side by side can't both be in dead ends
+    const UNSOLVABLE: &str =
+        "00000003 44444444 44444444 EE                                                              ";
+
+    #[test]
+    fn solve_all_keeps_input_order() {
+        let puzzles = [SOLVABLE[1], UNSOLVABLE, SOLVABLE[0]]
+            .map(|line| line.parse::<Puzzle>().unwrap())
+            .to_vec();
+        let results = solve_all(puzzles);
+
+        let seeds = results.iter().map(|r| r.seed).collect::<Vec<u32>>();
+        assert_eq!(seeds, [2, 3, 1]);
+        assert!(results[0].solution.is_ok() && results[0].difficulty.is_some());
+        assert!(results[1].solution.is_err() && results[1].difficulty.is_none());
+        assert!(results[2].solution.is_ok() && results[2].difficulty.is_some());
+    }
+
+    #[test]
+    fn solve_lines_counts_and_orders_results_across_chunks() {
+        let dump_line = format!("00000004 {}", &SOLVABLE[0][27..]);
+        let input = [
+            SOLVABLE[0],
+            "not a puzzle",
+            UNSOLVABLE,
+            "",
+            SOLVABLE[1],
+            &dump_line,
+            SOLVABLE[0],
+        ]
+        .join("\n");
+
+        let mut output = vec![];
+        let summary = solve_lines(input.as_bytes(), &mut output, 2).unwrap();
+
+        assert_eq!(summary.solved, 3);
+        assert_eq!(summary.unsolved, 1);
+        assert_eq!(
+            summary.invalid,
+            [
+                (2, ParsePuzzleError::InvalidLength(12)),
+                (6, ParsePuzzleError::MissingCounts),
+            ]
+        );
+
+        let output = String::from_utf8(output).unwrap();
+        let lines = output
+            .lines()
+            .map(|line| line.split(' ').take(2).collect::<Vec<&str>>())
+            .collect::<Vec<Vec<&str>>>();
+        assert_eq!(
+            lines,
+            [
+                ["00000001", "solved"],
+                ["00000003", "unsolved"],
+                ["00000002", "solved"],
+                ["00000001", "solved"],
+            ]
+        );
+    }
+}
@@ -0,0 +1,231 @@
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Not};
+
+use crate::puzzle::BoardState;
+
+// bit index of a cell is y * 8 + x, so each row is one byte
+const FIRST_COLUMN: u64 = 0x0101_0101_0101_0101;
+const LAST_COLUMN: u64 = 0x8080_8080_8080_8080;
+const FIRST_ROW: u64 = 0xff;
+
+/// A set of cells on the 8x8 board, one bit per cell
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Bitboard(pub u64);
+
+impl Bitboard {
+    pub const EMPTY: Bitboard = Bitboard(0);
+    pub const FULL: Bitboard = Bitboard(u64::MAX);
+
+    pub fn cell(x: usize, y: usize) -> Self {
+        Bitboard(1 << (y * 8 + x))
+    }
+
+    pub fn row(y: usize) -> Self {
+        Bitboard(FIRST_ROW << (y * 8))
+    }
+
+    pub fn column(x: usize) -> Self {
+        Bitboard(FIRST_COLUMN << x)
+    }
+
+    pub fn contains(self, x: usize, y: usize) -> bool {
+        self.0 & (1 << (y * 8 + x)) != 0
+    }
+
+    pub fn count(self) -> u8 {
+        self.0.count_ones() as u8
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// The lowest cell in the set, scanning rows from the top
+    pub fn first(self) -> Option<(usize, usize)> {
+        if self.0 == 0 {
+            None
+        } else {
+            let i = self.0.trailing_zeros() as usize;
+            Some((i % 8, i / 8))
+        }
+    }
+
+    pub fn cells(self) -> impl Iterator<Item = (usize, usize)> {
+        let mut bits = self.0;
+        std::iter::from_fn(move || {
+            if bits == 0 {
+                return None;
+            }
+            let i = bits.trailing_zeros() as usize;
+            bits &= bits - 1;
+            Some((i % 8, i / 8))
+        })
+    }
+
+    // each shift moves every cell one step, dropping the ones that fall off the board. A cell is
+    // set in `left()` when the cell to its right was set, and so on.
+    pub fn left(self) -> Self {
+        Bitboard((self.0 >> 1) & !LAST_COLUMN)
+    }
+
+    pub fn right(self) -> Self {
+        Bitboard((self.0 << 1) & !FIRST_COLUMN)
+    }
+
+    pub fn up(self) -> Self {
+        Bitboard(self.0 >> 8)
+    }
+
+    pub fn down(self) -> Self {
+        Bitboard(self.0 << 8)
+    }
+
+    /// Cells with at least one orthogonal neighbour in the set
+    pub fn neighbors(self) -> Self {
+        self.left() | self.right() | self.up() | self.down()
+    }
+
+    /// Cells with at least two orthogonal neighbours in the set
+    pub fn two_neighbors(self) -> Self {
+        let [a, b, c, d] = [self.left(), self.right(), self.up(), self.down()];
+        (a & b) | (a & c) | (a & d) | (b & c) | (b & d) | (c & d)
+    }
+
+    /// Cells with exactly one orthogonal neighbour in the set
+    pub fn one_neighbor(self) -> Self {
+        self.neighbors() & !self.two_neighbors()
+    }
+
+    /// Top left corner of every 2x2 block lying entirely inside the set
+    pub fn blocks_2x2(self) -> Self {
+        let pair = self & self.left();
+        pair & pair.up()
+    }
+
+    /// The 2x2 block whose top left corner is x,y
+    pub fn block_2x2(x: usize, y: usize) -> Self {
+        let corner = Bitboard::cell(x, y);
+        let pair = corner | corner.right();
+        pair | pair.down()
+    }
+
+    /// The 3x3 block centred on x,y, clipped to the board
+    pub fn block_3x3(x: usize, y: usize) -> Self {
+        let center = Bitboard::cell(x, y);
+        let row = center | center.left() | center.right();
+        row | row.up() | row.down()
+    }
+
+    /// Every cell of `within` connected to the seed cells
+    pub fn flood(self, within: Bitboard) -> Self {
+        let mut filled = self & within;
+        loop {
+            let next = (filled | filled.neighbors()) & within;
+            if next == filled {
+                return filled;
+            }
+            filled = next;
+        }
+    }
+}
+
+impl BitAnd for Bitboard {
+    type Output = Self;
+    fn bitand(self, rhs: Self) -> Self {
+        Bitboard(self.0 & rhs.0)
+    }
+}
+
+impl BitOr for Bitboard {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self {
+        Bitboard(self.0 | rhs.0)
+    }
+}
+
+impl Not for Bitboard {
+    type Output = Self;
+    fn not(self) -> Self {
+        Bitboard(!self.0)
+    }
+}
+
+impl BitAndAssign for Bitboard {
+    fn bitand_assign(&mut self, rhs: Self) {
+        self.0 &= rhs.0
+    }
+}
+
+impl BitOrAssign for Bitboard {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0
+    }
+}
+
+/// Board state stored as one bitboard per kind of cell. Cells in none of them are empty.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Board {
+    pub walls: Bitboard,
+    pub paths: Bitboard,
+    pub enemies: Bitboard,
+    pub treasures: Bitboard,
+}
+
+impl Board {
+    pub fn get(&self, x: usize, y: usize) -> BoardState {
+        if self.walls.contains(x, y) {
+            BoardState::Wall
+        } else if self.paths.contains(x, y) {
+            BoardState::Path
+        } else if self.enemies.contains(x, y) {
+            BoardState::Enemy
+        } else if self.treasures.contains(x, y) {
+            BoardState::Treasure
+        } else {
+            BoardState::Empty
+        }
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, state: BoardState) {
+        let cell = Bitboard::cell(x, y);
+        for mask in [
+            &mut self.walls,
+            &mut self.paths,
+            &mut self.enemies,
+            &mut self.treasures,
+        ] {
+            *mask &= !cell;
+        }
+        match state {
+            BoardState::Wall => self.walls |= cell,
+            BoardState::Path => self.paths |= cell,
+            BoardState::Enemy => self.enemies |= cell,
+            BoardState::Treasure => self.treasures |= cell,
+            BoardState::Empty => (),
+        }
+    }
+
+    pub fn empty(&self) -> Bitboard {
+        !(self.walls | self.paths | self.enemies | self.treasures)
+    }
+
+    /// Cells that are known to be open, i.e. anything but a wall or an empty cell
+    pub fn open(&self) -> Bitboard {
+        self.paths | self.enemies | self.treasures
+    }
+
+    pub fn to_array(&self) -> [[BoardState; 8]; 8] {
+        std::array::from_fn(|y| std::array::from_fn(|x| self.get(x, y)))
+    }
+}
+
+impl From<[[BoardState; 8]; 8]> for Board {
+    fn from(board: [[BoardState; 8]; 8]) -> Self {
+        let mut bits = Board::default();
+        for (y, row) in board.iter().enumerate() {
+            for (x, &state) in row.iter().enumerate() {
+                bits.set(x, y, state);
+            }
+        }
+        bits
+    }
+}
@@ -0,0 +1,459 @@
+//! Measures where the counts, the board and the seed are drawn, from a screenshot of a puzzle
+//! whose contents are already known. The measurements are saved to a small text file that
+//! `DungeonCrawler` loads on startup, so a shift in the game's UI doesn't need new constants.
+use std::{fmt::Display, fs, io, path::Path};
+
+use image::{imageops, RgbaImage};
+
+use crate::{
+    layout::{self, Layout, Region},
+    puzzle::{BoardState, Puzzle},
+    vision::{self, PatternSearchError},
+    BOARD_BASE, LEFT_NUMS_BASE, LEFT_NUMS_OFFSETS, SAMPLE_POINT_SEED, SEED_BASE, TILE_SIZE,
+    TOP_NUMS_BASE, TOP_NUMS_OFFSETS,
+};
+
+/// Where `DungeonCrawler` looks for a calibration, relative to the working directory
+pub const CALIBRATION_FILE: &str = "calibration.txt";
+
+// How far from the current positions to look, in pixels of a 1x capture
+const SEARCH_RADIUS: isize = 6;
+
+// Tiles of the board allowed to read wrong, such as one under the mouse cursor
+const MAX_BOARD_MISMATCHES: usize = 2;
+
+/// Positions within the game, all measured on a 1x capture
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Calibration {
+    pub top_nums_base: (usize, usize),
+    pub top_nums_offsets: [usize; 8],
+    pub left_nums_base: (usize, usize),
+    pub left_nums_offsets: [usize; 8],
+    pub board_base: (usize, usize),
+    pub seed_base: (usize, usize),
+    // a pixel of the seed box that only has its colour on seeded puzzles
+    pub sample_point_seed: (usize, usize),
+}
+
+impl Calibration {
+    /// The positions measured by hand, which match the game as released
+    pub const DEFAULT: Calibration = Calibration {
+        top_nums_base: TOP_NUMS_BASE,
+        top_nums_offsets: TOP_NUMS_OFFSETS,
+        left_nums_base: LEFT_NUMS_BASE,
+        left_nums_offsets: LEFT_NUMS_OFFSETS,
+        board_base: BOARD_BASE,
+        seed_base: SEED_BASE,
+        sample_point_seed: SAMPLE_POINT_SEED,
+    };
+
+    /// Reads a calibration written by `save`. Keys missing from the file keep their default.
+    pub fn load(path: &Path) -> Result<Self, CalibrationError> {
+        let text = fs::read_to_string(path).map_err(CalibrationError::Io)?;
+        text.parse()
+    }
+
+    /// Writes one `key = values` line per position
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    // the same calibration with one position moved
+    fn shifted(
+        &self,
+        field: fn(&mut Calibration) -> &mut (usize, usize),
+        (dx, dy): (isize, isize),
+    ) -> Option<Self> {
+        let mut calibration = *self;
+        let point = field(&mut calibration);
+        *point = (
+            point.0.checked_add_signed(dx)?,
+            point.1.checked_add_signed(dy)?,
+        );
+        Some(calibration)
+    }
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl Display for Calibration {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let list = |values: &[usize]| {
+            values
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<String>>()
+                .join(" ")
+        };
+        let point = |(x, y): (usize, usize)| format!("{x} {y}");
+
+        writeln!(f, "# positions within the game, measured on a 1x capture")?;
+        writeln!(f, "top_nums_base = {}", point(self.top_nums_base))?;
+        writeln!(f, "top_nums_offsets = {}", list(&self.top_nums_offsets))?;
+        writeln!(f, "left_nums_base = {}", point(self.left_nums_base))?;
+        writeln!(f, "left_nums_offsets = {}", list(&self.left_nums_offsets))?;
+        writeln!(f, "board_base = {}", point(self.board_base))?;
+        writeln!(f, "seed_base = {}", point(self.seed_base))?;
+        writeln!(f, "sample_point_seed = {}", point(self.sample_point_seed))
+    }
+}
+
+impl std::str::FromStr for Calibration {
+    type Err = CalibrationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut calibration = Calibration::DEFAULT;
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || CalibrationError::InvalidLine(i + 1);
+            let (key, values) = line.split_once('=').ok_or_else(invalid)?;
+            let values = values
+                .split_whitespace()
+                .map(|v| v.parse::<usize>())
+                .collect::<Result<Vec<usize>, _>>()
+                .map_err(|_| invalid())?;
+
+            let point = |values: &[usize]| match *values {
+                [x, y] => Ok((x, y)),
+                _ => Err(invalid()),
+            };
+            let offsets = |values: &[usize]| values.try_into().map_err(|_| invalid());
+            match key.trim() {
+                "top_nums_base" => calibration.top_nums_base = point(&values)?,
+                "top_nums_offsets" => calibration.top_nums_offsets = offsets(&values)?,
+                "left_nums_base" => calibration.left_nums_base = point(&values)?,
+                "left_nums_offsets" => calibration.left_nums_offsets = offsets(&values)?,
+                "board_base" => calibration.board_base = point(&values)?,
+                "seed_base" => calibration.seed_base = point(&values)?,
+                "sample_point_seed" => calibration.sample_point_seed = point(&values)?,
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(calibration)
+    }
+}
+
+#[derive(Debug)]
+pub enum CalibrationError {
+    Search(PatternSearchError),
+    // the known counts couldn't be found along the top or the left
+    TopNumsNotFound,
+    LeftNumsNotFound,
+    // no position of the board reads back the known monsters and chests
+    BoardNotFound,
+    SeedNotFound,
+    Io(io::Error),
+    // a line of a calibration file, counting from 1
+    InvalidLine(usize),
+}
+
+impl Display for CalibrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use CalibrationError::*;
+        match self {
+            Search(e) => write!(f, "unable to find the game: {e:?}"),
+            TopNumsNotFound => write!(f, "the top wall counts don't match the puzzle"),
+            LeftNumsNotFound => write!(f, "the left wall counts don't match the puzzle"),
+            BoardNotFound => write!(f, "the board doesn't match the puzzle"),
+            SeedNotFound => write!(f, "the seed doesn't match the puzzle"),
+            Io(e) => write!(f, "{e}"),
+            InvalidLine(n) => write!(f, "line {n} isn't a valid position"),
+        }
+    }
+}
+
+impl std::error::Error for CalibrationError {}
+
+impl From<PatternSearchError> for CalibrationError {
+    fn from(e: PatternSearchError) -> Self {
+        CalibrationError::Search(e)
+    }
+}
+
+/// Measures every position from a screenshot of `puzzle`, at any scale. The puzzle should be
+/// freshly started, with no walls or paths placed, and its seed is only used if it has one.
+/// Positions are searched near the defaults, so the game can't have moved far.
+pub fn calibrate(image: &RgbaImage, puzzle: &Puzzle) -> Result<Calibration, CalibrationError> {
+    let game = layout::find_layout(image)?.crop(image);
+    let mut calibration = Calibration::DEFAULT;
+
+    let default = Layout::unscaled(Calibration::DEFAULT);
+    let found = find_counts(&game, puzzle.get_top_clues(), true, |i| default.top_num(i))
+        .ok_or(CalibrationError::TopNumsNotFound)?;
+    let (x, offsets) = spacing(found.map(|(x, _)| x));
+    calibration.top_nums_base = (x, found[0].1);
+    calibration.top_nums_offsets = offsets;
+
+    let found = find_counts(&game, puzzle.get_left_clues(), false, |i| {
+        default.left_num(i)
+    })
+    .ok_or(CalibrationError::LeftNumsNotFound)?;
+    let (y, offsets) = spacing(found.map(|(_, y)| y));
+    calibration.left_nums_base = (found[0].0, y);
+    calibration.left_nums_offsets = offsets;
+
+    calibration = find_board(&game, calibration, puzzle.get_board())?;
+
+    if let Some(seed) = puzzle.get_seed() {
+        calibration = find_seed(&game, calibration, seed)?;
+    }
+    Ok(calibration)
+}
+
+// every shift within the search radius, nearest first, so ties go to the smaller move
+fn shifts() -> Vec<(isize, isize)> {
+    let range = -SEARCH_RADIUS..=SEARCH_RADIUS;
+    let mut shifts = range
+        .clone()
+        .flat_map(|dy| range.clone().map(move |dx| (dx, dy)))
+        .collect::<Vec<(isize, isize)>>();
+    shifts.sort_by_key(|(dx, dy)| dx.abs() + dy.abs());
+    shifts
+}
+
+// whether a region lies inside the game, leaving a column and row spare for the seed reader,
+// which looks one pixel past the end of its strip
+fn fits(game: &RgbaImage, (x, y, width, height): Region) -> bool {
+    x + width < game.width() && y + height < game.height()
+}
+
+// the top left corner of each count, found by moving the default tiles until the known digit
+// reads back with the widest margin. All eight share one shift across the line, but each gets
+// its own shift along it.
+fn find_counts(
+    game: &RgbaImage,
+    expected: [u8; 8],
+    horizontal: bool,
+    default: impl Fn(usize) -> Region,
+) -> Option<[(usize, usize); 8]> {
+    let margin = |(x, y): (isize, isize), digit: u8| {
+        let (x, y) = (u32::try_from(x).ok()?, u32::try_from(y).ok()?);
+        let size = TILE_SIZE as u32;
+        if !fits(game, (x, y, size, size)) {
+            return None;
+        }
+        let tile = imageops::crop_imm(game, x, y, size, size).to_image();
+        let read = vision::parse_digit(&tile).ok()?;
+        (read.digit == digit).then_some(read.margin)
+    };
+
+    let mut best: Option<(f32, [(usize, usize); 8])> = None;
+    'across: for across in -SEARCH_RADIUS..=SEARCH_RADIUS {
+        let mut total = 0.0;
+        let mut found = [(0, 0); 8];
+        for (i, &digit) in expected.iter().enumerate() {
+            let (x, y, _, _) = default(i);
+            let (x, y) = (x as isize, y as isize);
+            let read = (-SEARCH_RADIUS..=SEARCH_RADIUS)
+                .map(|along| match horizontal {
+                    true => (x + along, y + across),
+                    false => (x + across, y + along),
+                })
+                .filter_map(|position| Some((margin(position, digit)?, position)))
+                .max_by(|a, b| a.0.total_cmp(&b.0));
+            let Some((margin, (x, y))) = read else {
+                continue 'across;
+            };
+            total += margin;
+            found[i] = (x as usize, y as usize);
+        }
+        if best.is_none_or(|(score, _)| total > score) {
+            best = Some((total, found));
+        }
+    }
+    best.map(|(_, found)| found)
+}
+
+// splits where each count starts along its line into the start of the line and how far each
+// count sits from an even spacing of one tile
+fn spacing(starts: [usize; 8]) -> (usize, [usize; 8]) {
+    let unspaced: [usize; 8] = std::array::from_fn(|i| starts[i] - TILE_SIZE * i);
+    let base = *unspaced.iter().min().unwrap();
+    (base, unspaced.map(|start| start - base))
+}
+
+// the board position that reads back the puzzle's monsters and chests
+fn find_board(
+    game: &RgbaImage,
+    calibration: Calibration,
+    expected: [[BoardState; 8]; 8],
+) -> Result<Calibration, CalibrationError> {
+    let mut best: Option<(f32, Calibration)> = None;
+    for shift in shifts() {
+        let Some(candidate) = calibration.shifted(|c| &mut c.board_base, shift) else {
+            continue;
+        };
+        if !fits(game, Layout::unscaled(candidate).tile(7, 7)) {
+            continue;
+        }
+        // a board of mostly floor reads back the same a few pixels either way, so among the
+        // positions that read the tiles right the closest match wins
+        let tiles = vision::parse_tiles(game, &candidate);
+        let mismatches = tiles
+            .iter()
+            .flatten()
+            .zip(expected.iter().flatten())
+            .filter(|(read, expected)| read.contents.board_state() != **expected)
+            .count();
+        if mismatches > MAX_BOARD_MISMATCHES {
+            continue;
+        }
+        let distance = tiles.iter().flatten().map(|tile| tile.distance).sum();
+        if best.is_none_or(|(score, _)| distance < score) {
+            best = Some((distance, candidate));
+        }
+    }
+    best.map(|(_, calibration)| calibration)
+        .ok_or(CalibrationError::BoardNotFound)
+}
+
+// the nearest shift of the seed box that reads back the known seed. The sample point moves
+// with it, since both are drawn as part of the same box.
+fn find_seed(
+    game: &RgbaImage,
+    calibration: Calibration,
+    seed: u32,
+) -> Result<Calibration, CalibrationError> {
+    shifts()
+        .into_iter()
+        .filter_map(|shift| {
+            calibration
+                .shifted(|c| &mut c.seed_base, shift)?
+                .shifted(|c| &mut c.sample_point_seed, shift)
+        })
+        .filter(|candidate| fits(game, Layout::unscaled(*candidate).seed()))
+        .find(|candidate| vision::parse_seed(game, candidate).is_ok_and(|read| read == Some(seed)))
+        .ok_or(CalibrationError::SeedNotFound)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::imageops::FilterType;
+
+    use super::*;
+
+    // a fresh puzzle with monsters and chests to find the board by, and a seed holding
+    // every digit the other tests don't
+    fn puzzle() -> Puzzle {
+        "    Seed: 23452480
+    3 3 3 5 1 4 3 5
+    ----------------
+ 5| _ _ _ _ T _ _ _
+ 2| _ T _ _ _ _ _ _
+ 2| _ _ _ _ _ _ _ _
+ 4| _ _ _ _ _ _ _ _
+ 6| _ _ _ _ _ _ _ _
+ 1| _ _ _ _ _ _ E _
+ 5| _ E _ _ _ _ _ _
+ 2| _ _ E _ _ _ _ _"
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn calibrate_finds_default_positions() {
+        let image = puzzle().render(false);
+        assert_eq!(calibrate(&image, &puzzle()).unwrap(), Calibration::DEFAULT);
+    }
+
+    #[test]
+    fn calibrate_finds_default_positions_when_scaled() {
+        let image = puzzle().render(false);
+        let (width, height) = image.dimensions();
+        let image = imageops::resize(&image, width * 2, height * 2, FilterType::Nearest);
+        assert_eq!(calibrate(&image, &puzzle()).unwrap(), Calibration::DEFAULT);
+    }
+
+    #[test]
+    fn calibrate_follows_moved_seed_box() {
+        let mut image = puzzle().render(false);
+        let seed_box = imageops::crop_imm(&image, 95, 95, 85, 20).to_image();
+        imageops::replace(&mut image, &seed_box, 97, 96);
+
+        let calibration = calibrate(&image, &puzzle()).unwrap();
+        let (x, y) = Calibration::DEFAULT.seed_base;
+        assert_eq!(calibration.seed_base, (x + 2, y + 1));
+        let (x, y) = Calibration::DEFAULT.sample_point_seed;
+        assert_eq!(calibration.sample_point_seed, (x + 2, y + 1));
+    }
+
+    #[test]
+    fn calibrate_rejects_wrong_seed() {
+        let image = puzzle().render(false);
+        let text = puzzle().to_string().replace("23452480", "23452481");
+        let wrong = text.parse::<Puzzle>().unwrap();
+        assert!(matches!(
+            calibrate(&image, &wrong),
+            Err(CalibrationError::SeedNotFound)
+        ));
+    }
+
+    #[test]
+    fn calibration_round_trips_through_text() {
+        let calibration = Calibration {
+            top_nums_base: (68, 88),
+            top_nums_offsets: [0, 1, 0, 2, 1, 0, 1, 0],
+            board_base: (71, 130),
+            seed_base: (103, 101),
+            sample_point_seed: (101, 97),
+            ..Calibration::DEFAULT
+        };
+        assert_eq!(
+            calibration.to_string().parse::<Calibration>().unwrap(),
+            calibration
+        );
+    }
+
+    #[test]
+    fn calibration_round_trips_through_file() {
+        let path = std::env::temp_dir().join(format!("calibration-{}.txt", std::process::id()));
+        let calibration = Calibration {
+            left_nums_base: (40, 127),
+            left_nums_offsets: [1, 0, 0, 1, 0, 0, 1, 0],
+            ..Calibration::DEFAULT
+        };
+        calibration.save(&path).unwrap();
+        let loaded = Calibration::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), calibration);
+    }
+
+    #[test]
+    fn missing_keys_keep_their_default() {
+        let calibration = "# moved board\nboard_base = 70 131\n"
+            .parse::<Calibration>()
+            .unwrap();
+        let expected = Calibration {
+            board_base: (70, 131),
+            ..Calibration::DEFAULT
+        };
+        assert_eq!(calibration, expected);
+    }
+
+    #[test]
+    fn invalid_lines_are_reported() {
+        for text in [
+            "board_base = 70",
+            "board_base 70 131",
+            "board_base = 70 -1",
+            "top_nums_offsets = 0 0 0",
+            "seed = 1 2",
+        ] {
+            let text = format!("# positions\n{text}\n");
+            assert!(
+                matches!(
+                    text.parse::<Calibration>(),
+                    Err(CalibrationError::InvalidLine(2))
+                ),
+                "{text}"
+            );
+        }
+    }
+}
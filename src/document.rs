@@ -0,0 +1,183 @@
+//! Versioned JSON and TOML documents for exchanging puzzles and solutions.
+//!
+//! A document has the following fields:
+//!
+//! - `version`: schema version, currently 1. Documents with any other version are rejected.
+//! - `seed`: the in-game seed, omitted for generated puzzles.
+//! - `top_counts`, `left_counts`: the walls required in each column and row.
+//! - `givens`: 8 rows of 8 cells holding only `"Empty"`, `"Enemy"` and `"Treasure"`.
+//! - `solution`: optional 8 rows of 8 cells, the givens plus every `"Wall"` and `"Path"` placed
+//!   so far. Omitted when nothing has been placed.
+//! - `moves`: optional list of `[x, y, "Wall" | "Path"]` in the order they were made.
+
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+use crate::puzzle::{self, BoardState, Placeable, Puzzle};
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PuzzleDocument {
+    pub version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u32>,
+    pub top_counts: [u8; 8],
+    pub left_counts: [u8; 8],
+    pub givens: [[BoardState; 8]; 8],
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub solution: Option<[[BoardState; 8]; 8]>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub moves: Vec<(usize, usize, Placeable)>,
+}
+
+#[derive(Debug)]
+pub enum DocumentError {
+    Json(serde_json::Error),
+    TomlRead(toml::de::Error),
+    TomlWrite(toml::ser::Error),
+    UnsupportedVersion(u32),
+    InvalidSeed(u32),
+    InvalidCount(u8),
+    // a wall or path among the givens, or a solution that disagrees with them
+    InvalidCell { x: usize, y: usize },
+}
+
+impl Display for DocumentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use DocumentError::*;
+        match self {
+            Json(e) => write!(f, "invalid JSON: {e}"),
+            TomlRead(e) => write!(f, "invalid TOML: {e}"),
+            TomlWrite(e) => write!(f, "unable to write TOML: {e}"),
+            UnsupportedVersion(v) => {
+                write!(
+                    f,
+                    "schema version {v} is not supported, expected {SCHEMA_VERSION}"
+                )
+            }
+            InvalidSeed(seed) => write!(f, "seed {seed} is out of range"),
+            InvalidCount(n) => write!(f, "wall count {n} is out of range"),
+            InvalidCell { x, y } => write!(f, "{x},{y}: cell doesn't match the givens"),
+        }
+    }
+}
+
+impl std::error::Error for DocumentError {}
+
+// read on its own first so a newer document is reported as such, rather than as a missing field
+#[derive(Deserialize)]
+struct Version {
+    version: u32,
+}
+
+impl PuzzleDocument {
+    pub fn new(puzzle: &Puzzle, moves: &[(usize, usize, Placeable)]) -> Self {
+        let board = puzzle.get_board();
+        let givens = board.map(|row| {
+            row.map(|state| match state {
+                BoardState::Wall | BoardState::Path => BoardState::Empty,
+                state => state,
+            })
+        });
+        let placed = board
+            .iter()
+            .flatten()
+            .any(|&s| s == BoardState::Wall || s == BoardState::Path);
+
+        PuzzleDocument {
+            version: SCHEMA_VERSION,
+            seed: puzzle.get_seed(),
+            top_counts: puzzle.get_top_clues(),
+            left_counts: puzzle.get_left_clues(),
+            givens,
+            solution: placed.then_some(board),
+            moves: moves.to_vec(),
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, DocumentError> {
+        serde_json::to_string_pretty(self).map_err(DocumentError::Json)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, DocumentError> {
+        let Version { version } = serde_json::from_str(json).map_err(DocumentError::Json)?;
+        check_version(version)?;
+        serde_json::from_str(json).map_err(DocumentError::Json)
+    }
+
+    pub fn to_toml(&self) -> Result<String, DocumentError> {
+        toml::to_string(self).map_err(DocumentError::TomlWrite)
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, DocumentError> {
+        let Version { version } = toml::from_str(text).map_err(DocumentError::TomlRead)?;
+        check_version(version)?;
+        toml::from_str(text).map_err(DocumentError::TomlRead)
+    }
+
+    /// Builds the puzzle described by the document, with the solution filled in if there is one
+    pub fn to_puzzle(&self) -> Result<Puzzle, DocumentError> {
+        check_version(self.version)?;
+        if let Some(seed) = self.seed.filter(|&s| s >= puzzle::SEED_MAX) {
+            return Err(DocumentError::InvalidSeed(seed));
+        }
+        if let Some(&n) = self
+            .top_counts
+            .iter()
+            .chain(self.left_counts.iter())
+            .find(|&&n| n >= 8)
+        {
+            return Err(DocumentError::InvalidCount(n));
+        }
+
+        let board = self.solution.unwrap_or(self.givens);
+        for (y, (givens, row)) in self.givens.iter().zip(board.iter()).enumerate() {
+            for (x, (&given, &state)) in givens.iter().zip(row.iter()).enumerate() {
+                let matches = match (given, state) {
+                    (BoardState::Empty, state) => {
+                        matches!(
+                            state,
+                            BoardState::Empty | BoardState::Wall | BoardState::Path
+                        )
+                    }
+                    (BoardState::Wall | BoardState::Path, _) => false,
+                    (given, state) => given == state,
+                };
+                if !matches {
+                    return Err(DocumentError::InvalidCell { x, y });
+                }
+            }
+        }
+
+        Ok(puzzle::new(
+            self.top_counts,
+            self.left_counts,
+            board,
+            self.seed,
+        ))
+    }
+}
+
+fn check_version(version: u32) -> Result<(), DocumentError> {
+    if version == SCHEMA_VERSION {
+        Ok(())
+    } else {
+        Err(DocumentError::UnsupportedVersion(version))
+    }
+}
+
+impl From<Puzzle> for PuzzleDocument {
+    fn from(puzzle: Puzzle) -> Self {
+        PuzzleDocument::new(&puzzle, &[])
+    }
+}
+
+impl TryFrom<PuzzleDocument> for Puzzle {
+    type Error = DocumentError;
+
+    fn try_from(document: PuzzleDocument) -> Result<Self, Self::Error> {
+        document.to_puzzle()
+    }
+}
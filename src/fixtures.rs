@@ -0,0 +1,200 @@
+//! Checks the screen reading against saved captures. Each `name.png` in a fixtures directory is
+//! paired with `name.txt` holding the puzzle it shows, in either format `Puzzle` parses, and
+//! every count, tile and seed read from the capture is compared with it.
+use std::{
+    fmt::Display,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use image::RgbaImage;
+
+use crate::{
+    calibration::Calibration,
+    puzzle::{BoardState, Puzzle},
+    vision,
+};
+
+/// Where the fixtures are kept, relative to the root of the repository
+pub const FIXTURES_DIR: &str = "fixtures";
+
+/// One field read differently from what the fixture expects
+#[derive(Clone, Debug, PartialEq)]
+pub enum Mismatch {
+    TopCount {
+        col: usize,
+        expected: u8,
+        found: u8,
+    },
+    LeftCount {
+        row: usize,
+        expected: u8,
+        found: u8,
+    },
+    Tile {
+        x: usize,
+        y: usize,
+        expected: BoardState,
+        found: BoardState,
+    },
+    Seed {
+        expected: Option<u32>,
+        found: Option<u32>,
+    },
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use Mismatch::*;
+        let seed = |seed: &Option<u32>| match seed {
+            Some(n) => format!("{n:08}"),
+            None => "none".to_string(),
+        };
+        match self {
+            TopCount {
+                col,
+                expected,
+                found,
+            } => write!(f, "column {col} count: expected {expected}, read {found}"),
+            LeftCount {
+                row,
+                expected,
+                found,
+            } => write!(f, "row {row} count: expected {expected}, read {found}"),
+            Tile {
+                x,
+                y,
+                expected,
+                found,
+            } => write!(f, "{x},{y}: expected{expected}, read{found}"),
+            Seed { expected, found } => {
+                write!(f, "seed: expected {}, read {}", seed(expected), seed(found))
+            }
+        }
+    }
+}
+
+/// The outcome of checking one fixture
+pub struct FixtureResult {
+    pub name: PathBuf,
+    // every field read wrong, or why the capture couldn't be read at all
+    pub mismatches: Result<Vec<Mismatch>, String>,
+}
+
+impl FixtureResult {
+    pub fn passed(&self) -> bool {
+        self.mismatches.as_ref().is_ok_and(|m| m.is_empty())
+    }
+}
+
+impl Display for FixtureResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = self.name.display();
+        match &self.mismatches {
+            Ok(mismatches) if mismatches.is_empty() => write!(f, "{name}: ok"),
+            Ok(mismatches) => {
+                write!(f, "{name}: {} mismatches", mismatches.len())?;
+                for mismatch in mismatches {
+                    write!(f, "\n  {mismatch}")?;
+                }
+                Ok(())
+            }
+            Err(reason) => write!(f, "{name}: {reason}"),
+        }
+    }
+}
+
+/// Reads the puzzle from a capture and lists every field that differs from `expected`
+pub fn check_capture(
+    image: &RgbaImage,
+    expected: &Puzzle,
+    calibration: &Calibration,
+) -> Result<Vec<Mismatch>, vision::VisionError> {
+    let found = vision::parse_puzzle_from_image(image, calibration)?;
+    Ok(compare(expected, &found))
+}
+
+/// Every field of `found` that differs from `expected`, counts first, then the board row by
+/// row, then the seed
+pub fn compare(expected: &Puzzle, found: &Puzzle) -> Vec<Mismatch> {
+    let mut mismatches = vec![];
+
+    let counts = expected
+        .get_top_clues()
+        .into_iter()
+        .zip(found.get_top_clues());
+    for (col, (expected, found)) in counts.enumerate() {
+        if expected != found {
+            mismatches.push(Mismatch::TopCount {
+                col,
+                expected,
+                found,
+            });
+        }
+    }
+    let counts = expected
+        .get_left_clues()
+        .into_iter()
+        .zip(found.get_left_clues());
+    for (row, (expected, found)) in counts.enumerate() {
+        if expected != found {
+            mismatches.push(Mismatch::LeftCount {
+                row,
+                expected,
+                found,
+            });
+        }
+    }
+
+    let (board, read) = (expected.get_board(), found.get_board());
+    for y in 0..8 {
+        for x in 0..8 {
+            if board[y][x] != read[y][x] {
+                mismatches.push(Mismatch::Tile {
+                    x,
+                    y,
+                    expected: board[y][x],
+                    found: read[y][x],
+                });
+            }
+        }
+    }
+
+    if expected.get_seed() != found.get_seed() {
+        mismatches.push(Mismatch::Seed {
+            expected: expected.get_seed(),
+            found: found.get_seed(),
+        });
+    }
+    mismatches
+}
+
+/// Checks every PNG in `dir` against the puzzle file next to it, in order of name. A capture
+/// without a puzzle file, or with one that doesn't parse, is reported as a failure.
+pub fn check_fixtures(dir: &Path, calibration: &Calibration) -> io::Result<Vec<FixtureResult>> {
+    let mut captures = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<PathBuf>>>()?;
+    captures.retain(|path| path.extension().is_some_and(|ext| ext == "png"));
+    captures.sort();
+
+    Ok(captures
+        .into_iter()
+        .map(|capture| FixtureResult {
+            mismatches: check_fixture(&capture, calibration),
+            name: capture,
+        })
+        .collect())
+}
+
+fn check_fixture(capture: &Path, calibration: &Calibration) -> Result<Vec<Mismatch>, String> {
+    let puzzle_file = capture.with_extension("txt");
+    let expected = fs::read_to_string(&puzzle_file)
+        .map_err(|e| format!("unable to read {}: {e}", puzzle_file.display()))?
+        .parse::<Puzzle>()
+        .map_err(|e| format!("invalid puzzle in {}: {e}", puzzle_file.display()))?;
+    let image = image::open(capture)
+        .map_err(|e| format!("unable to open capture: {e}"))?
+        .to_rgba8();
+    check_capture(&image, &expected, calibration).map_err(|e| format!("unable to read: {e:?}"))
+}
@@ -0,0 +1,143 @@
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    bitboard::{Bitboard, Board},
+    puzzle::{self, BoardState, Puzzle},
+};
+
+// give up on a layout after this many attempts and let the caller know
+const MAX_ATTEMPTS: usize = 10_000;
+
+/// Generates a random dungeon with a unique solution. The returned puzzle only contains the
+/// givens (enemies and treasures) along with the wall counts derived from the solution. Returns
+/// None if no layout worked out within `MAX_ATTEMPTS` attempts.
+pub fn generate<R: Rng>(rng: &mut R) -> Option<Puzzle> {
+    (0..MAX_ATTEMPTS).find_map(|_| try_generate(rng))
+}
+
+fn try_generate<R: Rng>(rng: &mut R) -> Option<Puzzle> {
+    let solution = generate_dungeon(rng)?;
+
+    let mut top_counts = [0; 8];
+    let mut left_counts = [0; 8];
+    for row in 0..8 {
+        for col in 0..8 {
+            if solution[row][col] == BoardState::Wall {
+                top_counts[col] += 1;
+                left_counts[row] += 1;
+            }
+        }
+    }
+    // a full row or column of walls can't be shown by the game
+    if top_counts.iter().chain(left_counts.iter()).any(|&n| n >= 8) {
+        return None;
+    }
+
+    if puzzle::new(top_counts, left_counts, solution, None)
+        .validate()
+        .is_err()
+    {
+        return None;
+    }
+
+    // strip the walls and paths, leaving only the givens
+    let givens = solution.map(|row| {
+        row.map(|state| match state {
+            BoardState::Wall | BoardState::Path => BoardState::Empty,
+            state => state,
+        })
+    });
+    let puzzle = puzzle::new(top_counts, left_counts, givens, None);
+    if puzzle.count_solutions(2).len() != 1 {
+        return None;
+    }
+
+    Some(puzzle)
+}
+
+// carves treasure rooms and hallways out of a board full of walls, then places an enemy in every
+// dead end
+fn generate_dungeon<R: Rng>(rng: &mut R) -> Option<[[BoardState; 8]; 8]> {
+    let mut open = Bitboard::EMPTY;
+    let mut treasures = Bitboard::EMPTY;
+    // cells that hallways may not be carved through
+    let mut reserved = Bitboard::EMPTY;
+    let mut in_room = Bitboard::EMPTY;
+
+    let room_count = *[0, 1, 1, 1, 2].choose(rng).unwrap();
+    for _ in 0..room_count {
+        let cx = rng.gen_range(1..7usize);
+        let cy = rng.gen_range(1..7usize);
+        let room = Bitboard::block_3x3(cx, cy);
+        if !(room & reserved).is_empty() {
+            return None;
+        }
+
+        open |= room;
+        reserved |= room;
+        in_room |= room;
+        treasures |= Bitboard::cell(
+            rng.gen_range(cx - 1..=cx + 1),
+            rng.gen_range(cy - 1..=cy + 1),
+        );
+
+        // only the exit may be open on the boundary
+        let boundary = room.neighbors() & !room;
+        let exits = (boundary & !reserved)
+            .cells()
+            .collect::<Vec<(usize, usize)>>();
+        let &(ex, ey) = exits.choose(rng)?;
+        reserved |= boundary;
+        open |= Bitboard::cell(ex, ey);
+    }
+
+    // without a room, the hallways start from a random cell
+    if room_count == 0 {
+        open |= Bitboard::cell(rng.gen_range(0..8), rng.gen_range(0..8));
+    }
+
+    // grow the hallways one cell at a time, keeping them a single tile wide
+    let target = rng.gen_range(28..=38);
+    while open.count() < target {
+        let blocks = open.blocks_2x2();
+        let frontier = (!open & !reserved & (open & !in_room).neighbors())
+            .cells()
+            .filter(|&(x, y)| ((open | Bitboard::cell(x, y)).blocks_2x2() & !blocks).is_empty())
+            .collect::<Vec<(usize, usize)>>();
+        let Some(&(x, y)) = frontier.choose(rng) else {
+            break;
+        };
+        open |= Bitboard::cell(x, y);
+    }
+
+    // enemies sit in every dead end
+    let enemies = open & !in_room & open.one_neighbor();
+
+    let board = Board {
+        walls: !open,
+        paths: open & !treasures & !enemies,
+        enemies,
+        treasures,
+    };
+    Some(board.to_array())
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn generated_puzzles_have_one_valid_solution() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..5 {
+            let puzzle = generate(&mut rng).unwrap();
+            assert_eq!(puzzle.count_solutions(2).len(), 1);
+
+            let mut solution = puzzle.clone();
+            solution.solve().unwrap();
+            assert_eq!(solution.validate(), Ok(()));
+        }
+    }
+}
@@ -0,0 +1,203 @@
+//! Finds the game in a capture at any integer scale. The positions in `Calibration` are all
+//! measured on a 1x capture, and `Layout` maps them to wherever the game actually is.
+use image::{imageops, RgbaImage};
+
+use crate::{
+    calibration::Calibration,
+    vision::{PatternSearchError, GAME_SIZE},
+    DND_PATTERN, TILE_SIZE,
+};
+
+// Largest scale searched for, enough for the game fullscreen on a 4K display
+pub const MAX_SCALE: u32 = 4;
+
+// Size of the strip holding the seed, starting at the seed base
+const SEED_SIZE: (usize, usize) = (72, 7);
+
+/// A region of a capture as x, y, width and height
+pub type Region = (u32, u32, u32, u32);
+
+/// The position of the game in a capture, how many capture pixels each pixel of the game is
+/// drawn with, and where things are within the game
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Layout {
+    pub origin: (u32, u32),
+    pub scale: u32,
+    pub calibration: Calibration,
+}
+
+impl Layout {
+    pub fn new(origin: (u32, u32), scale: u32) -> Self {
+        Self {
+            origin,
+            scale,
+            calibration: Calibration::DEFAULT,
+        }
+    }
+
+    /// A capture already cut to the game at 1x, where every position is used as measured
+    pub fn unscaled(calibration: Calibration) -> Self {
+        Self {
+            origin: (0, 0),
+            scale: 1,
+            calibration,
+        }
+    }
+
+    pub fn with_calibration(self, calibration: Calibration) -> Self {
+        Self {
+            calibration,
+            ..self
+        }
+    }
+
+    /// Size of the whole game in the capture
+    pub fn size(&self) -> (u32, u32) {
+        (GAME_SIZE.0 * self.scale, GAME_SIZE.1 * self.scale)
+    }
+
+    /// Maps a point measured on a 1x capture of the game into the capture. The point lands in
+    /// the middle of the block of pixels drawn for it.
+    pub fn point(&self, x: usize, y: usize) -> (u32, u32) {
+        (
+            self.origin.0 + x as u32 * self.scale + self.scale / 2,
+            self.origin.1 + y as u32 * self.scale + self.scale / 2,
+        )
+    }
+
+    /// Maps a region measured on a 1x capture of the game into the capture
+    pub fn region(&self, x: usize, y: usize, width: usize, height: usize) -> Region {
+        (
+            self.origin.0 + x as u32 * self.scale,
+            self.origin.1 + y as u32 * self.scale,
+            width as u32 * self.scale,
+            height as u32 * self.scale,
+        )
+    }
+
+    pub fn game(&self) -> Region {
+        let (width, height) = self.size();
+        (self.origin.0, self.origin.1, width, height)
+    }
+
+    /// The tile at col,row of the board
+    pub fn tile(&self, col: usize, row: usize) -> Region {
+        let base = self.calibration.board_base;
+        self.region(
+            base.0 + col * TILE_SIZE,
+            base.1 + row * TILE_SIZE,
+            TILE_SIZE,
+            TILE_SIZE,
+        )
+    }
+
+    /// The middle of the tile at col,row, where clicks on it should go
+    pub fn tile_center(&self, col: usize, row: usize) -> (u32, u32) {
+        let base = self.calibration.board_base;
+        self.point(
+            base.0 + col * TILE_SIZE + TILE_SIZE / 2,
+            base.1 + row * TILE_SIZE + TILE_SIZE / 2,
+        )
+    }
+
+    /// The wall count above column i
+    pub fn top_num(&self, i: usize) -> Region {
+        let (base, offsets) = (
+            self.calibration.top_nums_base,
+            self.calibration.top_nums_offsets,
+        );
+        self.region(
+            base.0 + offsets[i] + TILE_SIZE * i,
+            base.1,
+            TILE_SIZE,
+            TILE_SIZE,
+        )
+    }
+
+    /// The wall count left of row i
+    pub fn left_num(&self, i: usize) -> Region {
+        let (base, offsets) = (
+            self.calibration.left_nums_base,
+            self.calibration.left_nums_offsets,
+        );
+        self.region(
+            base.0,
+            base.1 + offsets[i] + TILE_SIZE * i,
+            TILE_SIZE,
+            TILE_SIZE,
+        )
+    }
+
+    /// The strip the seed digits are drawn in
+    pub fn seed(&self) -> Region {
+        let base = self.calibration.seed_base;
+        self.region(base.0, base.1, SEED_SIZE.0, SEED_SIZE.1)
+    }
+
+    /// Cuts the game out of a capture and brings it down to 1x, so it can be parsed with the
+    /// 1x positions
+    pub fn crop(&self, image: &RgbaImage) -> RgbaImage {
+        let (x, y, width, height) = self.game();
+        let width = width.min(image.width() - x);
+        let height = height.min(image.height() - y);
+        self.downscale(&imageops::crop_imm(image, x, y, width, height).to_image())
+    }
+
+    /// Brings an image already cut to the game down to 1x, keeping the top left pixel of every
+    /// block
+    pub fn downscale(&self, game: &RgbaImage) -> RgbaImage {
+        if self.scale == 1 {
+            return game.clone();
+        }
+        RgbaImage::from_fn(
+            game.width() / self.scale,
+            game.height() / self.scale,
+            |x, y| *game.get_pixel(x * self.scale, y * self.scale),
+        )
+    }
+}
+
+/// Finds the game by the colours along the top of its window, trying every scale up to
+/// `MAX_SCALE`. The game has to fit entirely inside the capture.
+pub fn find_layout(image: &RgbaImage) -> Result<Layout, PatternSearchError> {
+    use PatternSearchError::*;
+
+    for scale in 1..=MAX_SCALE {
+        // every pixel of the pattern is repeated `scale` times along the row
+        let pattern = DND_PATTERN
+            .chunks(4)
+            .flat_map(|pixel| std::iter::repeat_n(pixel, scale as usize))
+            .flatten()
+            .copied()
+            .collect::<Vec<u8>>();
+
+        let width = image.width() as usize;
+        let found = image
+            .as_raw()
+            .windows(pattern.len())
+            .enumerate()
+            .filter(|(i, arr)| i % 4 == 0 && *arr == pattern.as_slice())
+            .map(|(i, _)| ((i / 4 % width) as u32, (i / 4 / width) as u32))
+            .collect::<Vec<(u32, u32)>>();
+        // a scaled pattern also matches on the rows repeating it, only the first one counts
+        let matches = found
+            .iter()
+            .filter(|&&(x, y)| y == 0 || !found.contains(&(x, y - 1)))
+            .copied()
+            .collect::<Vec<(u32, u32)>>();
+
+        match matches.len() {
+            0 => continue,
+            1 => {
+                let layout = Layout::new(matches[0], scale);
+                let (x, y, width, height) = layout.game();
+                if x + width > image.width() || y + height > image.height() {
+                    return Err(OutOfBounds);
+                }
+                return Ok(layout);
+            }
+            n => return Err(MultipleResults(n)),
+        }
+    }
+    Err(NotFound)
+}
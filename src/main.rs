@@ -8,15 +8,85 @@ use std::{
     time::{Duration, Instant},
 };
 
-use win_screenshot::prelude::*;
-
 use dungeons_n_diagrams::*;
 mod tex;
 
 fn main() {
     // tex::decode_all_textures();
 
-    let mut dc = DungeonCrawler::new().unwrap();
-    dc.solve_loop();
-    // dc.read_loop();
+    let args = std::env::args().collect::<Vec<String>>();
+    // check the screen reading against the saved captures, failing if any field is misread
+    if let [_, flag, rest @ ..] = &args[..] {
+        if flag == "--fixtures" && rest.len() <= 1 {
+            let dir = rest
+                .first()
+                .map_or(fixtures::FIXTURES_DIR, |dir| dir.as_str());
+            // the captures were taken with the positions as released, not as calibrated locally
+            let calibration = calibration::Calibration::DEFAULT;
+            let results = fixtures::check_fixtures(dir.as_ref(), &calibration).unwrap();
+            for result in results.iter() {
+                println!("{result}");
+            }
+            let failed = results.iter().filter(|result| !result.passed()).count();
+            println!("{} passed, {failed} failed", results.len() - failed);
+            std::process::exit(if failed == 0 { 0 } else { 1 });
+        }
+    }
+    if let [_, flag, input, output] = &args[..] {
+        // solve a corpus of seeds offline instead of driving the game
+        if flag == "--batch" {
+            let summary = batch::solve_corpus(input.as_ref(), output.as_ref()).unwrap();
+            for (line, reason) in summary.invalid.iter() {
+                println!("line {line}: {reason}");
+            }
+            println!(
+                "{} solved, {} unsolved, {} invalid in {:.1?}",
+                summary.solved,
+                summary.unsolved,
+                summary.invalid.len(),
+                summary.time
+            );
+            return;
+        }
+    }
+    // measure the positions from a screenshot of a fresh puzzle, given as a file in either of
+    // the formats `Puzzle` parses
+    if let [_, flag, image, puzzle_file] = &args[..] {
+        if flag == "--calibrate" {
+            if let Err(e) = calibrate(image, puzzle_file) {
+                eprintln!("Unable to calibrate: {e}");
+                std::process::exit(1);
+            }
+            return;
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        let mut dc = DungeonCrawler::new(backend::WindowsBackend::new()).unwrap();
+        dc.solve_loop();
+        // dc.read_loop();
+    }
+    #[cfg(all(not(windows), feature = "x11"))]
+    {
+        let backend = backend::X11Backend::new().unwrap();
+        let mut dc = DungeonCrawler::new(backend).unwrap();
+        dc.solve_loop();
+    }
+    #[cfg(all(not(windows), not(feature = "x11")))]
+    println!("No game backend for this platform, build with --features x11 or use --batch");
+}
+
+fn calibrate(image: &str, puzzle_file: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let image = image::open(image)
+        .map_err(|e| format!("unable to open {image}: {e}"))?
+        .to_rgba8();
+    let puzzle = std::fs::read_to_string(puzzle_file)
+        .map_err(|e| format!("unable to read {puzzle_file}: {e}"))?
+        .parse::<puzzle::Puzzle>()
+        .map_err(|e| format!("invalid puzzle in {puzzle_file}: {e}"))?;
+    let calibration = calibration::calibrate(&image, &puzzle)?;
+    calibration.save(calibration::CALIBRATION_FILE.as_ref())?;
+    print!("{calibration}");
+    Ok(())
 }
@@ -1,35 +1,20 @@
-use std::fmt::Display;
-
-const SEED_MAX: u32 = 99999999;
-
-const LEFT: (i8, i8) = (-1, 0);
-const UP: (i8, i8) = (0, -1);
-const RIGHT: (i8, i8) = (1, 0);
-const DOWN: (i8, i8) = (0, 1);
-const NEIGHBORS_4: [(i8, i8); 4] = [LEFT, UP, RIGHT, DOWN];
-
-const TREASURE_BOUNDARIES: [(isize, isize); 12] = [
-    (-1, -2),
-    (0, -2),
-    (1, -2),
-    (-1, 2),
-    (0, 2),
-    (1, 2),
-    (-2, -1),
-    (-2, 0),
-    (-2, 1),
-    (2, -1),
-    (2, 0),
-    (2, 1),
-];
+use std::{fmt::Display, str::FromStr};
+
+use crate::bitboard::{Bitboard, Board};
+#[cfg(feature = "serde")]
+use crate::document::PuzzleDocument;
+
+pub const SEED_MAX: u32 = 99999999;
 
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Placeable {
     Wall,
     Path,
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BoardState {
     Empty,
     Enemy,
@@ -38,6 +23,189 @@ pub enum BoardState {
     Path,
 }
 
+/// Why a board can't be completed
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ContradictionKind {
+    AdjacentEnemies,
+    EnemyBesideTreasure,
+    EnemyWithoutPath,
+    EnemyWithManyPaths,
+    NoRoomForTreasure,
+    NoWallsLeft,
+    DeadEnd,
+    InvalidState,
+    InvalidSolution,
+    WalledOff,
+    RowWalls,
+    ColumnWalls,
+}
+
+impl ContradictionKind {
+    pub fn reason(&self) -> &'static str {
+        use ContradictionKind::*;
+        match self {
+            AdjacentEnemies => "two enemies next to each other",
+            EnemyBesideTreasure => "enemy next to a chest",
+            EnemyWithoutPath => "no room for the enemy's path",
+            EnemyWithManyPaths => "enemy can only have one path",
+            NoRoomForTreasure => "no valid places for the treasure room",
+            NoWallsLeft => "no walls left for this row or column",
+            DeadEnd => "dead end without an enemy",
+            InvalidState => "cell can't take this state",
+            InvalidSolution => "filled board breaks the rules",
+            WalledOff => "path is cut off from the rest of the dungeon",
+            RowWalls => "not enough empty cells left for the row's walls",
+            ColumnWalls => "not enough empty cells left for the column's walls",
+        }
+    }
+}
+
+/// The part of the board at fault
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Location {
+    Cell(usize, usize),
+    Row(usize),
+    Column(usize),
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Location::Cell(x, y) => write!(f, "{x},{y}"),
+            Location::Row(row) => write!(f, "row {row}"),
+            Location::Column(col) => write!(f, "column {col}"),
+        }
+    }
+}
+
+// reasons a board can't be completed, along with where and the rule that found it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Contradiction {
+    pub location: Location,
+    pub rule: &'static str,
+    pub kind: ContradictionKind,
+}
+
+impl Contradiction {
+    fn new(location: Location, kind: ContradictionKind, rule: &'static str) -> Self {
+        Self {
+            location,
+            rule,
+            kind,
+        }
+    }
+
+    // a contradiction at a single cell
+    fn at((x, y): (usize, usize), kind: ContradictionKind, rule: &'static str) -> Self {
+        Self::new(Location::Cell(x, y), kind, rule)
+    }
+
+    // attributes the contradiction to the rule that was running when it surfaced
+    fn with_rule(self, rule: &'static str) -> Self {
+        Self { rule, ..self }
+    }
+}
+
+impl Display for Contradiction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} ({})",
+            self.location,
+            self.kind.reason(),
+            self.rule
+        )
+    }
+}
+
+impl std::error::Error for Contradiction {}
+
+/// A rule of the game broken by a filled board
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RuleViolation {
+    EmptyCell { x: usize, y: usize },
+    RowWallCount { row: usize, expected: u8, found: u8 },
+    ColumnWallCount { col: usize, expected: u8, found: u8 },
+    EnemyNotInDeadEnd { x: usize, y: usize },
+    DeadEndWithoutEnemy { x: usize, y: usize },
+    TreasureWithoutRoom { x: usize, y: usize },
+    // top left cell of a 2x2 block of path outside of a treasure room
+    WideHallway { x: usize, y: usize },
+    Disconnected { x: usize, y: usize },
+}
+
+impl RuleViolation {
+    pub fn cell(&self) -> Option<(usize, usize)> {
+        use RuleViolation::*;
+        match *self {
+            EmptyCell { x, y }
+            | EnemyNotInDeadEnd { x, y }
+            | DeadEndWithoutEnemy { x, y }
+            | TreasureWithoutRoom { x, y }
+            | WideHallway { x, y }
+            | Disconnected { x, y } => Some((x, y)),
+            RowWallCount { .. } | ColumnWallCount { .. } => None,
+        }
+    }
+}
+
+impl Display for RuleViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use RuleViolation::*;
+        match *self {
+            EmptyCell { x, y } => write!(f, "{x},{y}: cell is empty"),
+            RowWallCount {
+                row,
+                expected,
+                found,
+            } => write!(f, "row {row}: expected {expected} walls, found {found}"),
+            ColumnWallCount {
+                col,
+                expected,
+                found,
+            } => write!(f, "column {col}: expected {expected} walls, found {found}"),
+            EnemyNotInDeadEnd { x, y } => write!(f, "{x},{y}: enemy is not in a dead end"),
+            DeadEndWithoutEnemy { x, y } => write!(f, "{x},{y}: dead end without an enemy"),
+            TreasureWithoutRoom { x, y } => {
+                write!(f, "{x},{y}: treasure is not in a room with a single exit")
+            }
+            WideHallway { x, y } => write!(f, "{x},{y}: 2x2 block of path outside a room"),
+            Disconnected { x, y } => write!(f, "{x},{y}: path is not connected"),
+        }
+    }
+}
+
+/// Why a string couldn't be read as a puzzle
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParsePuzzleError {
+    // the one-line format has a fixed width, the grid a fixed number of lines
+    InvalidLength(usize),
+    // a line from a dump written before `serialize` included the wall counts, which can't be
+    // solved without them
+    MissingCounts,
+    InvalidSeed(String),
+    InvalidCount(char),
+    InvalidCell(char),
+    // a line, counting from 0, that doesn't match the layout it was written with
+    InvalidLine(usize),
+}
+
+impl Display for ParsePuzzleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use ParsePuzzleError::*;
+        match self {
+            InvalidLength(n) => write!(f, "unexpected length {n}"),
+            MissingCounts => write!(f, "no wall counts, dump the seed again to include them"),
+            InvalidSeed(seed) => write!(f, "invalid seed {seed:?}"),
+            InvalidCount(c) => write!(f, "invalid wall count {c:?}"),
+            InvalidCell(c) => write!(f, "invalid cell {c:?}"),
+            InvalidLine(n) => write!(f, "line {n} doesn't match the grid layout"),
+        }
+    }
+}
+
+impl std::error::Error for ParsePuzzleError {}
+
 #[derive(Clone, Copy)]
 struct Treasure {
     x: u8,
@@ -54,23 +222,102 @@ impl Treasure {
             solved: false,
         }
     }
+
+    // centre of the room at `pos` in `pos_mask`. A centre off the board wraps around to a large
+    // value, which no room check accepts.
+    fn center(self, pos: usize) -> (usize, usize) {
+        let x = (self.x as usize + pos % 3).wrapping_sub(1);
+        let y = (self.y as usize + pos / 3).wrapping_sub(1);
+        (x, y)
+    }
+
+    // centres of the rooms the treasure could still be in
+    fn centers(self) -> impl Iterator<Item = (usize, usize)> {
+        (0..9)
+            .filter(move |pos| self.pos_mask & (1 << pos) != 0)
+            .map(move |pos| self.center(pos))
+    }
+
+    // whether x,y lies inside every room the treasure could still be in
+    fn always_covers(self, x: usize, y: usize) -> bool {
+        self.pos_mask != 0
+            && self
+                .centers()
+                .all(|(cx, cy)| Bitboard::block_3x3(cx, cy).contains(x, y))
+    }
+
+    // whether x,y is next to the room, once there is only one room left
+    fn room_borders(self, x: usize, y: usize) -> bool {
+        self.pos_mask.count_ones() == 1
+            && self.centers().all(|(cx, cy)| {
+                let room = Bitboard::block_3x3(cx, cy);
+                (room.neighbors() & !room).contains(x, y)
+            })
+    }
 }
 
+#[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "PuzzleDocument", try_from = "PuzzleDocument")
+)]
 pub struct Puzzle {
     seed: Option<u32>,
+    // walls shown on the puzzle for each row and column
+    top_clues: [u8; 8],
+    left_clues: [u8; 8],
     // walls still required for each row and column
     top_counts: [u8; 8],
     left_counts: [u8; 8],
-    // empty spaces available for each row and column
-    empty_counts_rows: [u8; 8],
-    empty_counts_cols: [u8; 8],
-    // full board state
-    board: [[BoardState; 8]; 8],
+    // full board state, one bitboard per kind of cell
+    board: Board,
     // for faster iteration of enemies/chests
     // enemies: Vec<(u8, u8)>,
     treasures: Vec<Treasure>,
 }
 
+/// Where a hint came from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HintSource {
+    /// Deduced by the named rule
+    Rule(&'static str),
+    /// None of the rules apply, so the move was read off a solution found by searching, which
+    /// needed this many guesses
+    Search { guesses: usize },
+}
+
+impl Display for HintSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HintSource::Rule(rule) => write!(f, "{rule}"),
+            HintSource::Search { guesses } => write!(f, "search, {guesses} guesses"),
+        }
+    }
+}
+
+/// A single deduction along with the reasoning behind it
+#[derive(Clone, Debug)]
+pub struct Hint {
+    pub x: usize,
+    pub y: usize,
+    pub placeable: Placeable,
+    pub source: HintSource,
+    // cells that justify the move, e.g. the enemy whose path is forced
+    pub cells: Vec<(usize, usize)>,
+    pub explanation: String,
+}
+
+impl Display for Hint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?} at {},{}: {} ({})",
+            self.placeable, self.x, self.y, self.explanation, self.source
+        )
+    }
+}
+
 pub fn new(
     top_counts: [u8; 8],
     left_counts: [u8; 8],
@@ -86,14 +333,14 @@ pub fn new(
 
     let mut enemies = Vec::new();
     let mut treasures = Vec::new();
-    let mut empty_count_rows = [0; 8];
-    let mut empty_count_cols = [0; 8];
+    let mut wall_count_rows = [0; 8];
+    let mut wall_count_cols = [0; 8];
     for y in 0..8u8 {
         for x in 0..8u8 {
             match board[y as usize][x as usize] {
-                BoardState::Empty => {
-                    empty_count_rows[y as usize] += 1;
-                    empty_count_cols[x as usize] += 1;
+                BoardState::Wall => {
+                    wall_count_rows[y as usize] += 1;
+                    wall_count_cols[x as usize] += 1;
                 }
                 BoardState::Enemy => enemies.push((x, y)),
                 BoardState::Treasure => treasures.push(Treasure::new(x, y, 0b1_1111_1111)),
@@ -104,111 +351,660 @@ pub fn new(
 
     Puzzle {
         seed,
-        top_counts,
-        left_counts,
-        empty_counts_rows: empty_count_rows,
-        empty_counts_cols: empty_count_cols,
-        board,
+        top_clues: top_counts,
+        left_clues: left_counts,
+        top_counts: std::array::from_fn(|i| top_counts[i].saturating_sub(wall_count_cols[i])),
+        left_counts: std::array::from_fn(|i| left_counts[i].saturating_sub(wall_count_rows[i])),
+        board: board.into(),
         // enemies,
         treasures,
     }
 }
 
-type Solver = fn(&mut Puzzle, &mut Vec<(usize, usize, Placeable)>) -> bool;
+// rules report whether they changed the board, or why the board can't be solved
+type Solver = fn(&mut Puzzle, &mut Vec<(usize, usize, Placeable)>) -> Result<bool, Contradiction>;
+
+// each rule with its name and how hard its moves are for a human to spot, used by
+// `SolveReport::difficulty`
+const SOLVERS: &[(Solver, &str, f32)] = &[
+    (Puzzle::solve_trivial, "solve_trivial", 1.0),
+    (Puzzle::solve_enemies, "solve_enemies", 1.5),
+    (Puzzle::solve_deadend, "solve_deadend", 2.5),
+    (Puzzle::solve_corners, "solve_corners", 2.0),
+    (Puzzle::solve_treasures, "solve_treasures", 3.0),
+    (Puzzle::solve_floodfill, "solve_floodfill", 3.5),
+    (Puzzle::solve_inaccessible, "solve_inaccessible", 2.5),
+    (Puzzle::solve_forced_path, "solve_forced_path", 2.0),
+    (Puzzle::solve_2x2, "solve_2x2", 2.0),
+];
+
+/// Summary of how a puzzle was solved
+#[derive(Clone, Debug, Default)]
+pub struct SolveReport {
+    pub moves: Vec<(usize, usize, Placeable)>,
+    // moves found by each rule, in the order the rules run
+    pub rule_moves: Vec<(&'static str, usize)>,
+    // passes over the rule list before the rules stalled
+    pub rounds: usize,
+    pub backtracked: bool,
+    // guesses made by the search, including ones that were undone
+    pub guesses: usize,
+}
+
+impl SolveReport {
+    fn new() -> Self {
+        Self {
+            rule_moves: SOLVERS.iter().map(|(_, name, _)| (*name, 0)).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Rough difficulty of the puzzle for a human. Each rule is weighted by how hard it is to
+    /// spot, as listed alongside it in the rule table, and the score is the average weight per
+    /// deduced move plus a point per round. Needing to guess adds a large penalty on top.
+    pub fn difficulty(&self) -> f32 {
+        let mut weighted = 0.0;
+        let mut count = 0;
+        for (&(_, n), (_, _, weight)) in self.rule_moves.iter().zip(SOLVERS) {
+            weighted += weight * n as f32;
+            count += n;
+        }
+
+        let mut score = self.rounds as f32;
+        if count > 0 {
+            score += 10.0 * weighted / count as f32;
+        }
+        if self.backtracked {
+            score += 50.0 + self.guesses as f32;
+        }
+        score
+    }
+}
 
 impl Puzzle {
     pub fn get_seed(&self) -> Option<u32> {
         self.seed
     }
 
-    pub fn serialize(&self) {
-        print!("{:08} ", self.seed.unwrap());
-        for row in self.board {
+    /// Walls shown above each column, regardless of how many have been placed
+    pub fn get_top_clues(&self) -> [u8; 8] {
+        self.top_clues
+    }
+
+    /// Walls shown beside each row, regardless of how many have been placed
+    pub fn get_left_clues(&self) -> [u8; 8] {
+        self.left_clues
+    }
+
+    pub fn get_board(&self) -> [[BoardState; 8]; 8] {
+        self.board.to_array()
+    }
+
+    /// One line per puzzle: the seed, the top and left wall counts, then the 64 cells row by row.
+    /// An unseeded puzzle is written with dashes in place of the seed.
+    pub fn serialize(&self) -> String {
+        let mut line = match self.seed {
+            Some(n) => format!("{n:08} "),
+            None => "-------- ".to_string(),
+        };
+        for n in self.top_clues {
+            line.push(char::from(b'0' + n));
+        }
+        line.push(' ');
+        for n in self.left_clues {
+            line.push(char::from(b'0' + n));
+        }
+        line.push(' ');
+        line.push_str(&self.board_string());
+        line
+    }
+
+    /// The 64 cells of the board, row by row, as written by `serialize`
+    pub fn board_string(&self) -> String {
+        let mut cells = String::with_capacity(64);
+        for row in self.board.to_array() {
             for col in row {
-                match col {
-                    BoardState::Empty => print!(" "),
-                    BoardState::Enemy => print!("E"),
-                    BoardState::Treasure => print!("T"),
-                    BoardState::Wall => print!("W"),
-                    BoardState::Path => print!("P"),
+                cells.push(match col {
+                    BoardState::Empty => ' ',
+                    BoardState::Enemy => 'E',
+                    BoardState::Treasure => 'T',
+                    BoardState::Wall => 'W',
+                    BoardState::Path => 'P',
+                });
+            }
+        }
+        cells
+    }
+
+    pub fn solve(&mut self) -> Result<SolveReport, Contradiction> {
+        let mut report = SolveReport::new();
+
+        self.propagate(&mut report)?;
+
+        // the rules have stalled, guess the remaining cells
+        if !self.board.empty().is_empty() {
+            report.backtracked = true;
+            let solution = self.search(&mut report.guesses)?;
+            for row in 0..8 {
+                for col in 0..8 {
+                    match (self.board.get(col, row), solution.board.get(col, row)) {
+                        (BoardState::Empty, BoardState::Wall) => {
+                            report.moves.push((col, row, Placeable::Wall))
+                        }
+                        (BoardState::Empty, BoardState::Path) => {
+                            report.moves.push((col, row, Placeable::Path))
+                        }
+                        _ => (),
+                    }
                 }
             }
+            *self = solution;
+        } else {
+            self.check_solution()?;
         }
-        println!();
+
+        Ok(report)
     }
 
-    pub fn solve(&mut self) -> Vec<(usize, usize, Placeable)> {
+    // runs the deduction rules until none of them can make progress
+    fn propagate(&mut self, report: &mut SolveReport) -> Result<(), Contradiction> {
         let mut state_changed = true;
-        let mut moves = vec![];
-
-        let solvers: &[(Solver, &str)] = &[
-            (Puzzle::solve_trivial, "solve_trivial"),
-            (Puzzle::solve_enemies, "solve_enemies"),
-            (Puzzle::solve_deadend, "solve_deadend"),
-            (Puzzle::solve_corners, "solve_corners"),
-            (Puzzle::solve_treasures, "solve_treasures"),
-            // (Puzzle::solve_inaccessible, "solve_inaccessible"),
-            // (Puzzle::solve_forced_path, "solve_forced_path"),
-            // (Puzzle::solve_2x2, "solve_2x2"),
-        ];
-
-        // println!("{self}");
         while state_changed {
             state_changed = false;
+            report.rounds += 1;
+
+            for (i, (solver, name, _)) in SOLVERS.iter().enumerate() {
+                self.check_consistency()?;
+                let move_count = report.moves.len();
+                state_changed |= solver(self, &mut report.moves).map_err(|c| c.with_rule(name))?;
+                if let Some((_, n)) = report.rule_moves.get_mut(i) {
+                    *n += report.moves.len() - move_count;
+                }
+            }
+        }
+        self.check_consistency()
+    }
 
-            for (solver, name) in solvers {
-                println!("{name}");
-                state_changed |= solver(self, &mut moves);
-                println!("{self}");
+    // depth-first search over the remaining empty cells, branching on the most constrained one
+    // if both guesses fail, the contradiction from the last one is returned
+    fn search(&self, guesses: &mut usize) -> Result<Puzzle, Contradiction> {
+        let Some((col, row)) = self.most_constrained_cell() else {
+            self.check_solution()?;
+            return Ok(self.clone());
+        };
 
-                // let mut s = String::new();
-                // std::io::stdin().read_line(&mut s);
+        let mut contradiction = None;
+        for state in [BoardState::Wall, BoardState::Path] {
+            *guesses += 1;
+            let mut branch = self.clone();
+            let result = branch
+                .set_state(col, row, state)
+                .map_err(|c| c.with_rule("search"))
+                .and_then(|_| branch.propagate(&mut SolveReport::default()))
+                .and_then(|_| branch.search(guesses));
+            match result {
+                Ok(solution) => return Ok(solution),
+                Err(c) => contradiction = Some(c),
             }
         }
 
-        // println!("done solving, moves: {moves:?}");
-        moves
+        Err(contradiction.unwrap())
+    }
+
+    /// Finds the next move a player could deduce, or None if the board is already filled. The
+    /// rules are tried in order, and the search is only used once they have all stalled. Moves
+    /// found by the search aren't deductions, and are marked as such by `HintSource::Search`.
+    pub fn next_hint(&self) -> Result<Option<Hint>, Contradiction> {
+        for (solver, name, _) in SOLVERS {
+            let mut puzzle = self.clone();
+            let mut moves = vec![];
+            solver(&mut puzzle, &mut moves).map_err(|c| c.with_rule(name))?;
+            if let Some(&(x, y, placeable)) = moves.first() {
+                let (cells, explanation) = self.explain(&puzzle, name, x, y, placeable);
+                return Ok(Some(Hint {
+                    x,
+                    y,
+                    placeable,
+                    source: HintSource::Rule(name),
+                    cells,
+                    explanation,
+                }));
+            }
+        }
+
+        let mut puzzle = self.clone();
+        let report = puzzle.solve()?;
+        Ok(report.moves.first().map(|&(x, y, placeable)| Hint {
+            x,
+            y,
+            placeable,
+            source: HintSource::Search {
+                guesses: report.guesses,
+            },
+            cells: vec![],
+            explanation: "none of the rules apply, so this is taken from the solution".to_string(),
+        }))
+    }
+
+    // works out which cells led a rule to its move, and describes the reasoning. `after` is the
+    // puzzle as the rule left it, along with anything it narrowed down on the way.
+    fn explain(
+        &self,
+        after: &Puzzle,
+        rule: &str,
+        x: usize,
+        y: usize,
+        placeable: Placeable,
+    ) -> (Vec<(usize, usize)>, String) {
+        use BoardState::*;
+
+        let neighbors = |x: usize, y: usize| Bitboard::cell(x, y).neighbors().cells();
+        let empty_in_row =
+            |row: usize| (0..8).filter(move |&col| self.board.get(col, row) == Empty);
+        let empty_in_col =
+            |col: usize| (0..8).filter(move |&row| self.board.get(col, row) == Empty);
+
+        match rule {
+            "solve_trivial" => {
+                let (walls, empty) = (self.left_counts[y], self.empty_count_row(y));
+                if walls == empty || walls == 0 {
+                    let cells = empty_in_row(y).map(|col| (col, y)).collect();
+                    return if walls == 0 {
+                        (cells, format!("row {y} already has all of its walls"))
+                    } else {
+                        (
+                            cells,
+                            format!("row {y} needs {walls} walls and has {empty} empty cells"),
+                        )
+                    };
+                }
+                let (walls, empty) = (self.top_counts[x], self.empty_count_col(x));
+                let cells = empty_in_col(x).map(|row| (x, row)).collect();
+                if walls == 0 {
+                    (cells, format!("column {x} already has all of its walls"))
+                } else {
+                    (
+                        cells,
+                        format!("column {x} needs {walls} walls and has {empty} empty cells"),
+                    )
+                }
+            }
+            "solve_enemies" => {
+                let (ex, ey) = neighbors(x, y)
+                    .find(|&(nx, ny)| self.board.get(nx, ny) == Enemy)
+                    .unwrap_or((x, y));
+                match placeable {
+                    Placeable::Path => (
+                        vec![(ex, ey)],
+                        format!("the enemy at {ex},{ey} has no other way out"),
+                    ),
+                    Placeable::Wall => {
+                        let (px, py) = neighbors(ex, ey)
+                            .find(|&(nx, ny)| self.board.get(nx, ny) == Path)
+                            .unwrap_or((ex, ey));
+                        (
+                            vec![(ex, ey), (px, py)],
+                            format!(
+                                "the enemy at {ex},{ey} is a dead end and already has its path at {px},{py}"
+                            ),
+                        )
+                    }
+                }
+            }
+            "solve_deadend" => {
+                let cells = neighbors(x, y)
+                    .filter(|&(nx, ny)| self.board.get(nx, ny) == Wall)
+                    .collect();
+                (
+                    cells,
+                    format!("a path at {x},{y} would be a dead end without an enemy"),
+                )
+            }
+            "solve_corners" => {
+                let (px, py) = neighbors(x, y)
+                    .find(|&(nx, ny)| self.board.get(nx, ny) == Path)
+                    .unwrap_or((x, y));
+                let mut cells = vec![(px, py)];
+                cells.extend(neighbors(px, py).filter(|&(nx, ny)| self.board.get(nx, ny) == Wall));
+                (
+                    cells,
+                    format!(
+                        "the path at {px},{py} is walled in on two sides and must continue here"
+                    ),
+                )
+            }
+            "solve_treasures" => {
+                // the rooms a treasure could be in are narrowed down before anything is placed,
+                // so they are read from `after`
+                let treasures = &after.treasures;
+                if let Some(t) = treasures.iter().find(|t| t.always_covers(x, y)) {
+                    return (
+                        vec![(t.x as usize, t.y as usize)],
+                        format!(
+                            "every possible room for the treasure at {},{} covers this cell",
+                            t.x, t.y
+                        ),
+                    );
+                }
+
+                // otherwise the room is known, and the cell is on its boundary
+                let Some(t) = treasures.iter().find(|t| t.room_borders(x, y)) else {
+                    return (vec![], "this cell is part of a treasure room".to_string());
+                };
+                let explanation = match placeable {
+                    Placeable::Path => format!(
+                        "this is the only exit left for the room around the treasure at {},{}",
+                        t.x, t.y
+                    ),
+                    Placeable::Wall => format!(
+                        "the room around the treasure at {},{} can only have one exit",
+                        t.x, t.y
+                    ),
+                };
+                (vec![(t.x as usize, t.y as usize)], explanation)
+            }
+            "solve_floodfill" => match placeable {
+                Placeable::Path => {
+                    let cells = neighbors(x, y)
+                        .filter(|&(nx, ny)| ![Empty, Wall].contains(&self.board.get(nx, ny)))
+                        .collect();
+                    (
+                        cells,
+                        format!("this is the only way for the path next to {x},{y} to reach the rest of the dungeon"),
+                    )
+                }
+                Placeable::Wall => (
+                    vec![],
+                    format!("no path can reach {x},{y} without crossing a wall"),
+                ),
+            },
+            "solve_inaccessible" => {
+                let cells = neighbors(x, y)
+                    .filter(|&(nx, ny)| self.board.get(nx, ny) != Empty)
+                    .collect();
+                (
+                    cells,
+                    format!("{x},{y} is boxed in and can't be part of the path"),
+                )
+            }
+            "solve_forced_path" => {
+                let (px, py) = neighbors(x, y)
+                    .find(|&(nx, ny)| {
+                        self.board.get(nx, ny) == Path
+                            && neighbors(nx, ny)
+                                .filter(|&(ax, ay)| self.board.get(ax, ay) == Empty)
+                                .count()
+                                == 1
+                    })
+                    .unwrap_or((x, y));
+                (
+                    vec![(px, py)],
+                    format!("the path at {px},{py} has only one direction left to go"),
+                )
+            }
+            "solve_2x2" => {
+                let cells = (Bitboard::block_3x3(x, y) & self.board.open())
+                    .cells()
+                    .collect();
+                (
+                    cells,
+                    format!("a path at {x},{y} would make a hallway two tiles wide"),
+                )
+            }
+            _ => (vec![], format!("found by {rule}")),
+        }
+    }
+
+    /// Counts the solutions of the puzzle by exhaustive search, stopping early once `limit` have
+    /// been found. Each solution is returned as its own filled board.
+    pub fn count_solutions(&self, limit: usize) -> Vec<Puzzle> {
+        let mut solutions = vec![];
+        let mut puzzle = self.clone();
+        if limit > 0 && puzzle.propagate(&mut SolveReport::default()).is_ok() {
+            puzzle.collect_solutions(limit, &mut solutions);
+        }
+        solutions
+    }
+
+    fn collect_solutions(&self, limit: usize, solutions: &mut Vec<Puzzle>) {
+        let Some((col, row)) = self.most_constrained_cell() else {
+            if self.check_solution().is_ok() {
+                solutions.push(self.clone());
+            }
+            return;
+        };
+
+        for state in [BoardState::Wall, BoardState::Path] {
+            let mut branch = self.clone();
+            if branch.set_state(col, row, state).is_ok()
+                && branch.propagate(&mut SolveReport::default()).is_ok()
+            {
+                branch.collect_solutions(limit, solutions);
+                if solutions.len() >= limit {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Cells whose contents differ between two boards, e.g. two solutions of an ambiguous seed
+    pub fn differences(&self, other: &Puzzle) -> Vec<(usize, usize)> {
+        let mut cells = vec![];
+        for row in 0..8 {
+            for col in 0..8 {
+                if self.board.get(col, row) != other.board.get(col, row) {
+                    cells.push((col, row));
+                }
+            }
+        }
+        cells
     }
 
-    fn set_state(&mut self, col: usize, row: usize, state: BoardState) {
+    // the empty cell whose row or column has the fewest ways left to place its walls
+    fn most_constrained_cell(&self) -> Option<(usize, usize)> {
+        fn arrangements(empty: u8, walls: u8) -> u64 {
+            (0..walls as u64).fold(1, |acc, i| acc * (empty as u64 - i) / (i + 1))
+        }
+
+        let mut best = None;
+        let mut best_score = u64::MAX;
+        for (col, row) in self.board.empty().cells() {
+            let score = arrangements(self.empty_count_row(row), self.left_counts[row]).min(
+                arrangements(self.empty_count_col(col), self.top_counts[col]),
+            );
+            if score < best_score {
+                best_score = score;
+                best = Some((col, row));
+            }
+        }
+        best
+    }
+
+    // cheap checks for a partially filled board that can no longer lead to a solution
+    fn check_consistency(&self) -> Result<(), Contradiction> {
+        use ContradictionKind::*;
+        const RULE: &str = "check_consistency";
+
+        for i in 0..8 {
+            if self.left_counts[i] > self.empty_count_row(i) {
+                return Err(Contradiction::new(Location::Row(i), RowWalls, RULE));
+            }
+            if self.top_counts[i] > self.empty_count_col(i) {
+                return Err(Contradiction::new(Location::Column(i), ColumnWalls, RULE));
+            }
+        }
+
+        // every path needs two ways in or out, counting the empty cells around it
+        let open = !self.board.walls;
+        if let Some(cell) = (self.board.paths & !open.two_neighbors()).first() {
+            return Err(Contradiction::at(cell, DeadEnd, RULE));
+        }
+
+        Ok(())
+    }
+
+    // the first rule broken by a completely filled board
+    fn check_solution(&self) -> Result<(), Contradiction> {
+        use ContradictionKind::*;
+        const RULE: &str = "check_solution";
+
+        match self.validate() {
+            Ok(()) => Ok(()),
+            Err(violations) => Err(match violations[0] {
+                RuleViolation::RowWallCount { row, .. } => {
+                    Contradiction::new(Location::Row(row), RowWalls, RULE)
+                }
+                RuleViolation::ColumnWallCount { col, .. } => {
+                    Contradiction::new(Location::Column(col), ColumnWalls, RULE)
+                }
+                violation => Contradiction::at(violation.cell().unwrap(), InvalidSolution, RULE),
+            }),
+        }
+    }
+
+    /// Checks a completely filled board against every rule of the game, returning all the
+    /// rules it breaks
+    pub fn validate(&self) -> Result<(), Vec<RuleViolation>> {
+        let mut violations = vec![];
+
+        for (x, y) in self.board.empty().cells() {
+            violations.push(RuleViolation::EmptyCell { x, y });
+        }
+
+        // wall counts
+        for i in 0..8 {
+            let found = (self.board.walls & Bitboard::row(i)).count();
+            if found != self.left_clues[i] {
+                violations.push(RuleViolation::RowWallCount {
+                    row: i,
+                    expected: self.left_clues[i],
+                    found,
+                });
+            }
+            let found = (self.board.walls & Bitboard::column(i)).count();
+            if found != self.top_clues[i] {
+                violations.push(RuleViolation::ColumnWallCount {
+                    col: i,
+                    expected: self.top_clues[i],
+                    found,
+                });
+            }
+        }
+
+        let open_cells = !self.board.walls;
+
+        // each treasure must sit in a 3x3 room with a single exit
+        let mut in_room = Bitboard::EMPTY;
+        for treasure in self.treasures.iter() {
+            let (tx, ty) = (treasure.x as usize, treasure.y as usize);
+            let others = self.board.treasures & !Bitboard::cell(tx, ty);
+            let room = (ty.saturating_sub(1).max(1)..=(ty + 1).min(6))
+                .flat_map(|cy| {
+                    (tx.saturating_sub(1).max(1)..=(tx + 1).min(6)).map(move |cx| (cx, cy))
+                })
+                .map(|(cx, cy)| Bitboard::block_3x3(cx, cy))
+                .find(|&room| {
+                    let boundary = room.neighbors() & !room;
+                    (room & (self.board.walls | self.board.enemies | others)).is_empty()
+                        && (boundary & open_cells).count() == 1
+                });
+            match room {
+                Some(room) => in_room |= room,
+                None => violations.push(RuleViolation::TreasureWithoutRoom { x: tx, y: ty }),
+            }
+        }
+
+        // hallways are one tile wide
+        for (x, y) in open_cells.blocks_2x2().cells() {
+            if !(Bitboard::block_2x2(x, y) & !in_room).is_empty() {
+                violations.push(RuleViolation::WideHallway { x, y });
+            }
+        }
+
+        // enemies are exactly the dead ends
+        let enemies = self.board.enemies & open_cells & !open_cells.one_neighbor();
+        let dead_ends = open_cells & !self.board.enemies & !open_cells.two_neighbors();
+        for (x, y) in (enemies | dead_ends).cells() {
+            if enemies.contains(x, y) {
+                violations.push(RuleViolation::EnemyNotInDeadEnd { x, y });
+            } else {
+                violations.push(RuleViolation::DeadEndWithoutEnemy { x, y });
+            }
+        }
+
+        // every open cell is connected
+        if let Some((x, y)) = open_cells.first() {
+            let connected = Bitboard::cell(x, y).flood(open_cells);
+            for (x, y) in (open_cells & !connected).cells() {
+                violations.push(RuleViolation::Disconnected { x, y });
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    fn empty_count_row(&self, row: usize) -> u8 {
+        (self.board.empty() & Bitboard::row(row)).count()
+    }
+
+    fn empty_count_col(&self, col: usize) -> u8 {
+        (self.board.empty() & Bitboard::column(col)).count()
+    }
+
+    fn set_state(
+        &mut self,
+        col: usize,
+        row: usize,
+        state: BoardState,
+    ) -> Result<(), Contradiction> {
+        use ContradictionKind::*;
+        const RULE: &str = "set_state";
+
+        if self.board.get(col, row) != BoardState::Empty {
+            return Err(Contradiction::at((col, row), InvalidState, RULE));
+        }
         match state {
             BoardState::Wall => {
+                if self.top_counts[col] == 0 || self.left_counts[row] == 0 {
+                    return Err(Contradiction::at((col, row), NoWallsLeft, RULE));
+                }
                 self.top_counts[col] -= 1;
                 self.left_counts[row] -= 1;
-                self.empty_counts_cols[col] -= 1;
-                self.empty_counts_rows[row] -= 1;
             }
-            BoardState::Path => {
-                self.empty_counts_cols[col] -= 1;
-                self.empty_counts_rows[row] -= 1;
-            }
-            _ => panic!(),
+            BoardState::Path => (),
+            _ => return Err(Contradiction::at((col, row), InvalidState, RULE)),
         }
-        self.board[row][col] = state;
+        self.board.set(col, row, state);
+        Ok(())
     }
 
     // checks if a row or column can easily be filled in based on number of remaining walls
-    fn solve_trivial(&mut self, moves: &mut Vec<(usize, usize, Placeable)>) -> bool {
+    fn solve_trivial(
+        &mut self,
+        moves: &mut Vec<(usize, usize, Placeable)>,
+    ) -> Result<bool, Contradiction> {
         use BoardState::*;
 
         let mut state_changed = false;
 
         // check rows
         for row in 0..8 {
-            if self.left_counts[row] == self.empty_counts_rows[row] {
+            if self.left_counts[row] == self.empty_count_row(row) {
                 for col in 0..8 {
-                    if self.board[row][col] == Empty {
-                        self.set_state(col, row, Wall);
+                    if self.board.get(col, row) == Empty {
+                        self.set_state(col, row, Wall)?;
                         moves.push((col, row, Placeable::Wall));
                         state_changed = true;
                     }
                 }
-                self.left_counts[row] = 0;
-                self.empty_counts_rows[row] = 0;
-            } else if self.left_counts[row] == 0 && self.empty_counts_rows[row] > 0 {
+            } else if self.left_counts[row] == 0 && self.empty_count_row(row) > 0 {
                 for col in 0..8 {
-                    if self.board[row][col] == Empty {
-                        self.set_state(col, row, Path);
+                    if self.board.get(col, row) == Empty {
+                        self.set_state(col, row, Path)?;
                         moves.push((col, row, Placeable::Path));
                         state_changed = true;
                     }
@@ -218,449 +1014,385 @@ impl Puzzle {
 
         // check cols
         for col in 0..8 {
-            if self.top_counts[col] == self.empty_counts_cols[col] {
+            if self.top_counts[col] == self.empty_count_col(col) {
                 for row in 0..8 {
-                    if self.board[row][col] == Empty {
-                        self.set_state(col, row, Wall);
+                    if self.board.get(col, row) == Empty {
+                        self.set_state(col, row, Wall)?;
                         moves.push((col, row, Placeable::Wall));
                         state_changed = true;
                     }
                 }
-                self.top_counts[col] = 0;
-                self.empty_counts_cols[col] = 0;
-            } else if self.top_counts[col] == 0 && self.empty_counts_cols[col] > 0 {
+            } else if self.top_counts[col] == 0 && self.empty_count_col(col) > 0 {
                 for row in 0..8 {
-                    if self.board[row][col] == Empty {
-                        self.set_state(col, row, Path);
+                    if self.board.get(col, row) == Empty {
+                        self.set_state(col, row, Path)?;
                         moves.push((col, row, Placeable::Path));
                         state_changed = true;
                     }
                 }
             }
         }
-        state_changed
+        Ok(state_changed)
     }
 
     // checks if the tiles around an enemy can be solved based on the number of path/wall tiles
-    fn solve_enemies(&mut self, moves: &mut Vec<(usize, usize, Placeable)>) -> bool {
-        use BoardState::*;
+    fn solve_enemies(
+        &mut self,
+        moves: &mut Vec<(usize, usize, Placeable)>,
+    ) -> Result<bool, Contradiction> {
+        use ContradictionKind::*;
+        const RULE: &str = "solve_enemies";
+
+        let enemies = self.board.enemies;
+        if let Some(cell) = (enemies & enemies.neighbors()).first() {
+            return Err(Contradiction::at(cell, AdjacentEnemies, RULE));
+        }
+        if let Some(cell) = (enemies & self.board.treasures.neighbors()).first() {
+            return Err(Contradiction::at(cell, EnemyBesideTreasure, RULE));
+        }
 
         let mut state_changed = false;
 
-        for row in 0..8u8 {
-            for col in 0..8u8 {
-                if self.board[row as usize][col as usize] == Enemy {
-                    let mut path_count = 0;
-                    let mut empty_count = 0;
-                    let mut empty_cells = vec![];
-                    for offset in NEIGHBORS_4 {
-                        let x = col.wrapping_add_signed(offset.0);
-                        let y = row.wrapping_add_signed(offset.1);
-                        if x < 8 && y < 8 {
-                            match self.board[y as usize][x as usize] {
-                                Empty => {
-                                    empty_count += 1;
-                                    empty_cells.push((x, y));
-                                }
-                                Enemy => panic!("Cannot have two enemies next to each other"),
-                                Treasure => panic!("Cannot have enemy next to a chest"),
-                                Wall => (),
-                                Path => path_count += 1,
-                            }
-                        }
-                    }
-                    match path_count {
-                        0 => match empty_count {
-                            0 => panic!("No room for path"),
-                            1 => {
-                                let (col, row) = empty_cells[0];
-                                self.set_state(col as usize, row as usize, Path);
-                                moves.push((col as usize, row as usize, Placeable::Path));
-                                state_changed = true;
-                            }
-                            _ => (),
-                        },
-                        1 => match empty_count {
-                            0 => (),
-                            _ => {
-                                for (col, row) in empty_cells.iter() {
-                                    self.set_state(*col as usize, *row as usize, Wall);
-                                    moves.push((*col as usize, *row as usize, Placeable::Wall));
-                                    state_changed = true;
-                                }
-                            }
-                        },
-                        _ => panic!("Enemy can only have one path"),
-                    }
-                }
+        for cell in enemies.cells() {
+            let around = Bitboard::cell(cell.0, cell.1).neighbors();
+            let empty = around & self.board.empty();
+            let (state, placeable) = match ((around & self.board.paths).count(), empty.count()) {
+                (0, 0) => return Err(Contradiction::at(cell, EnemyWithoutPath, RULE)),
+                // the only way out has to be the path
+                (0, 1) => (BoardState::Path, Placeable::Path),
+                (0, _) => continue,
+                // the path is already there, so everything else is walled off
+                (1, _) => (BoardState::Wall, Placeable::Wall),
+                _ => return Err(Contradiction::at(cell, EnemyWithManyPaths, RULE)),
+            };
+            for (x, y) in empty.cells() {
+                self.set_state(x, y, state)?;
+                moves.push((x, y, placeable));
+                state_changed = true;
             }
         }
-        state_changed
+        Ok(state_changed)
     }
 
     // checks for dead ends
-    fn solve_deadend(&mut self, moves: &mut Vec<(usize, usize, Placeable)>) -> bool {
-        use BoardState::*;
+    fn solve_deadend(
+        &mut self,
+        moves: &mut Vec<(usize, usize, Placeable)>,
+    ) -> Result<bool, Contradiction> {
         let mut state_changed = false;
 
-        for row in 0..8u8 {
-            for col in 0..8u8 {
-                if self.board[row as usize][col as usize] == Empty {
-                    let mut inbounds_count = 0;
-                    let mut wall_count = 0;
-                    for offset in NEIGHBORS_4 {
-                        let x = col.wrapping_add_signed(offset.0);
-                        let y = row.wrapping_add_signed(offset.1);
-                        if x < 8 && y < 8 {
-                            inbounds_count += 1;
-                            if self.board[y as usize][x as usize] == Wall {
-                                wall_count += 1;
-                            }
-                        }
-                    }
-                    if wall_count + 1 >= inbounds_count {
-                        self.set_state(col as usize, row as usize, Wall);
-                        moves.push((col as usize, row as usize, Placeable::Wall));
-                        state_changed = true;
-                    }
-                }
-            }
+        // a path with fewer than two open neighbours would be a dead end without an enemy
+        let dead_ends = self.board.empty() & !(!self.board.walls).two_neighbors();
+        for (col, row) in dead_ends.cells() {
+            self.set_state(col, row, BoardState::Wall)?;
+            moves.push((col, row, Placeable::Wall));
+            state_changed = true;
         }
 
-        state_changed
+        Ok(state_changed)
     }
 
     // find possible cells that a treasure room must occupy
-    fn solve_treasures(&mut self, moves: &mut Vec<(usize, usize, Placeable)>) -> bool {
+    fn solve_treasures(
+        &mut self,
+        moves: &mut Vec<(usize, usize, Placeable)>,
+    ) -> Result<bool, Contradiction> {
+        use ContradictionKind::*;
         let mut state_changed = false;
 
         for i in 0..self.treasures.len() {
             if self.treasures[i].solved {
+                state_changed |= self.solve_treasure_exit(i, moves)?;
                 continue;
             }
+            let treasure = self.treasures[i];
             for pos in 0..9 {
-                if self.treasures[i].pos_mask & (1 << pos) > 0 {
-                    let offset_x: i8 = pos % 3 - 1;
-                    let offset_y: i8 = pos / 3 - 1;
-                    let cx = self.treasures[i].x.wrapping_add_signed(offset_x);
-                    let cy = self.treasures[i].y.wrapping_add_signed(offset_y);
-                    if (1..7).contains(&cx)
-                        && (1..7).contains(&cy)
-                        && self.is_treasure_room_valid(
-                            cx as usize,
-                            cy as usize,
-                            self.treasures[i].x as usize,
-                            self.treasures[i].y as usize,
-                        )
-                    {
-                    } else {
-                        self.treasures[i].pos_mask &= !(1 << pos);
-                    }
+                let (cx, cy) = treasure.center(pos);
+                if treasure.pos_mask & (1 << pos) != 0
+                    && !self.is_treasure_room_valid(
+                        cx,
+                        cy,
+                        treasure.x as usize,
+                        treasure.y as usize,
+                    )
+                {
+                    self.treasures[i].pos_mask &= !(1 << pos);
                 }
             }
-            match self.treasures[i].pos_mask.count_ones() {
-                0 => panic!("No valid places for chest"),
-                1 => {
-                    self.treasures[i].solved = true;
-                    let pos = self.treasures[i].pos_mask.ilog2() as u8;
-                    let center_x: u8 = pos % 3 + self.treasures[i].x - 1;
-                    let center_y: u8 = pos / 3 + self.treasures[i].y - 1;
-                    for offset_y in -1..=1 {
-                        for offset_x in -1..=1 {
-                            let x = center_x.wrapping_add_signed(offset_x);
-                            let y = center_y.wrapping_add_signed(offset_y);
-                            if self.board[y as usize][x as usize] == BoardState::Empty {
-                                self.set_state(x as usize, y as usize, BoardState::Path);
-                                moves.push((x as usize, y as usize, Placeable::Path));
-                                state_changed = true
-                            }
-                        }
-                    }
-                    // TODO
-                    // for (offset_x, offset_y) in TREASURE_BOUNDARIES {
-                    //     let x = center_x.wrapping_add_signed(offset_x as i8);
-                    //     let y = center_y.wrapping_add_signed(offset_y as i8);
-                    //     if x < 8 && y < 8 && self.board[y as usize][x as usize] == BoardState::Empty
-                    //     {
-                    //         self.set_state(x as usize, y as usize, BoardState::Wall);
-                    //         moves.push((x as usize, y as usize, Placeable::Wall));
-                    //         state_changed = true;
-                    //     }
-                    // }
-                }
-                _ => {
-                    // multiple positions valid, if any cells are common between them all, fill it with Path
-                    for offset_y in -2..=2 {
-                        for offset_x in -2..=2 {
-                            let x = self.treasures[i].x.wrapping_add_signed(offset_x);
-                            let y = self.treasures[i].y.wrapping_add_signed(offset_y);
-                            if x < 8
-                                && y < 8
-                                && self.board[y as usize][x as usize] == BoardState::Empty
-                            {
-                                let mut valid = true;
-                                for pos in 0..9 {
-                                    if self.treasures[i].pos_mask & (1 << pos) > 0 {
-                                        let center_x: u8 = pos % 3 + self.treasures[i].x - 1;
-                                        let center_y: u8 = pos / 3 + self.treasures[i].y - 1;
-                                        if center_x.abs_diff(x) > 1 || center_y.abs_diff(y) > 1 {
-                                            valid = false;
-                                            break;
-                                        }
-                                    }
-                                }
-                                if valid {
-                                    self.set_state(x as usize, y as usize, BoardState::Path);
-                                    moves.push((x as usize, y as usize, Placeable::Path));
-                                    state_changed = true;
-                                }
-                            }
-                        }
-                    }
-                }
+
+            let treasure = self.treasures[i];
+            if treasure.pos_mask == 0 {
+                return Err(Contradiction::at(
+                    (treasure.x as usize, treasure.y as usize),
+                    NoRoomForTreasure,
+                    "solve_treasures",
+                ));
+            }
+
+            // cells covered by every room left are part of the room whichever it turns out to be
+            let common = treasure.centers().fold(Bitboard::FULL, |common, (cx, cy)| {
+                common & Bitboard::block_3x3(cx, cy)
+            });
+            for (x, y) in (common & self.board.empty()).cells() {
+                self.set_state(x, y, BoardState::Path)?;
+                moves.push((x, y, Placeable::Path));
+                state_changed = true;
+            }
+
+            if treasure.pos_mask.count_ones() == 1 {
+                self.treasures[i].solved = true;
+                state_changed |= self.solve_treasure_exit(i, moves)?;
             }
         }
 
-        state_changed
+        Ok(state_changed)
     }
 
-    fn is_treasure_room_valid(&self, x: usize, y: usize, tx: usize, ty: usize) -> bool {
-        use BoardState::*;
-        // check interior of treasure room
-        for offset_y in -1..=1 {
-            for offset_x in -1..=1 {
-                let cx = x.wrapping_add_signed(offset_x);
-                let cy = y.wrapping_add_signed(offset_y);
-                // check if it would be out of bounds
-                if cx >= 8 || cy >= 8 {
-                    println!("    out of bounds");
-                    return false;
-                }
-                // check if a wall/enemy/other treasure is in bounds
-                match self.board[cy][cx] {
-                    Enemy => return false,
-                    Treasure => {
-                        if cx != tx || cy != ty {
-                            println!(
-                                "   other treasure chest in room: self: {tx},{ty} other: {cx},{cy}"
-                            );
-                            return false;
-                        }
-                    }
-                    Wall => {
-                        println!("    wall in bounds");
-                        return false;
-                    }
-                    _ => (),
-                }
-            }
+    // fills in the walls around a placed treasure room, leaving a single exit
+    fn solve_treasure_exit(
+        &mut self,
+        i: usize,
+        moves: &mut Vec<(usize, usize, Placeable)>,
+    ) -> Result<bool, Contradiction> {
+        use ContradictionKind::*;
+        let treasure = self.treasures[i];
+        let Some((cx, cy)) = treasure.centers().next() else {
+            return Ok(false);
+        };
+        let room = Bitboard::block_3x3(cx, cy);
+        let boundary = room.neighbors() & !room;
+        let candidates = boundary & self.board.empty();
+        if candidates.is_empty() {
+            return Ok(false);
         }
 
-        // check boundary of the treasure room
-        let mut empty_count = 0;
-        let mut path_count = 0;
-        for (offset_x, offset_y) in TREASURE_BOUNDARIES {
-            let cx = x.wrapping_add_signed(offset_x);
-            let cy = y.wrapping_add_signed(offset_y);
-            if cx >= 8 || cy >= 8 {
-                continue;
+        let exit_known = !(boundary & self.board.paths).is_empty();
+
+        // a cell can only be the exit if the rows and columns have room for walls on every other
+        // candidate
+        let mut exits = Bitboard::EMPTY;
+        let mut walls = candidates;
+        if !exit_known {
+            for (x, y) in candidates.cells() {
+                let others = candidates & !Bitboard::cell(x, y);
+                let fits = (0..8).all(|i| {
+                    (others & Bitboard::row(i)).count() <= self.left_counts[i]
+                        && (others & Bitboard::column(i)).count() <= self.top_counts[i]
+                });
+                if fits {
+                    exits |= Bitboard::cell(x, y);
+                }
             }
-            match self.board[cy][cx] {
-                Empty => empty_count += 1,
-                Enemy | Treasure => {
-                    println!("    treasure or enemy on boundary");
-                    return false;
+            walls &= !exits;
+
+            match exits.count() {
+                0 => {
+                    return Err(Contradiction::at(
+                        (treasure.x as usize, treasure.y as usize),
+                        NoRoomForTreasure,
+                        "solve_treasures",
+                    ))
+                }
+                1 => {
+                    let (x, y) = exits.first().unwrap();
+                    self.set_state(x, y, BoardState::Path)?;
+                    moves.push((x, y, Placeable::Path));
                 }
-                Wall => (),
-                Path => path_count += 1,
+                _ => (),
             }
         }
 
-        if empty_count == 0 || path_count > 1 {
-            println!("    empty_count: {empty_count}, path_count: {path_count}");
-            return false;
+        for (x, y) in walls.cells() {
+            self.set_state(x, y, BoardState::Wall)?;
+            moves.push((x, y, Placeable::Wall));
         }
 
-        true
+        Ok(exits.count() == 1 || !walls.is_empty())
     }
 
-    fn solve_corners(&mut self, moves: &mut Vec<(usize, usize, Placeable)>) -> bool {
-        const CORNERS: [[(i8, i8); 2]; 4] = [
-            [UP, LEFT],    // top_left
-            [UP, RIGHT],   // top_right
-            [DOWN, RIGHT], // bottom_right
-            [DOWN, LEFT],  // bottom_left
-        ];
+    fn is_treasure_room_valid(&self, x: usize, y: usize, tx: usize, ty: usize) -> bool {
+        // the room has to fit on the board
+        if !(1..7).contains(&x) || !(1..7).contains(&y) {
+            return false;
+        }
+        let room = Bitboard::block_3x3(x, y);
+        let boundary = room.neighbors() & !room;
 
-        use BoardState::*;
+        // nothing but its own treasure inside, and no enemies or treasures on the boundary
+        let others = self.board.treasures & !Bitboard::cell(tx, ty);
+        if !(room & (self.board.walls | self.board.enemies | others)).is_empty()
+            || !(boundary & (self.board.enemies | self.board.treasures)).is_empty()
+        {
+            return false;
+        }
+
+        // exactly one exit, which may not have been placed yet
+        match (boundary & self.board.paths).count() {
+            0 => !(boundary & self.board.empty()).is_empty(),
+            1 => true,
+            _ => false,
+        }
+    }
+
+    fn solve_corners(
+        &mut self,
+        moves: &mut Vec<(usize, usize, Placeable)>,
+    ) -> Result<bool, Contradiction> {
         let mut state_changed = false;
 
-        for row in 0..8u8 {
-            for col in 0..8u8 {
-                if self.board[row as usize][col as usize] == Path {
-                    for (i, dirs) in CORNERS.iter().enumerate() {
-                        let mut wall_count = 0;
-                        let mut inbounds_count = 0;
-                        for dir in dirs {
-                            let x = col.wrapping_add_signed(dir.0);
-                            let y = row.wrapping_add_signed(dir.1);
-                            if x < 8 && y < 8 {
-                                inbounds_count += 1;
-                                if self.board[y as usize][x as usize] == Wall {
-                                    wall_count += 1;
-                                }
-                            }
-                        }
-                        if wall_count == inbounds_count {
-                            for dir in CORNERS[(i + 2) % 4] {
-                                let x = col.wrapping_add_signed(dir.0);
-                                let y = row.wrapping_add_signed(dir.1);
-                                if x < 8 && y < 8 && self.board[y as usize][x as usize] == Empty {
-                                    self.set_state(x as usize, y as usize, Path);
-                                    moves.push((x as usize, y as usize, Placeable::Path));
-                                    state_changed = true;
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+        // cells whose neighbour on each side is a wall or off the board
+        let walls = self.board.walls;
+        let blocked_up = walls.down() | Bitboard::row(0);
+        let blocked_down = walls.up() | Bitboard::row(7);
+        let blocked_left = walls.right() | Bitboard::column(0);
+        let blocked_right = walls.left() | Bitboard::column(7);
+
+        // a path in a corner has to carry on through both of the other sides
+        let paths = self.board.paths;
+        let turns_right = paths & blocked_left & (blocked_up | blocked_down);
+        let turns_left = paths & blocked_right & (blocked_up | blocked_down);
+        let turns_down = paths & blocked_up & (blocked_left | blocked_right);
+        let turns_up = paths & blocked_down & (blocked_left | blocked_right);
+        let opened = turns_right.right() | turns_left.left() | turns_down.down() | turns_up.up();
+
+        for (x, y) in (opened & self.board.empty()).cells() {
+            self.set_state(x, y, BoardState::Path)?;
+            moves.push((x, y, Placeable::Path));
+            state_changed = true;
         }
 
-        state_changed
+        Ok(state_changed)
     }
 
     /// checks if single empty squares have no empty neighbors (must be a wall)
-    fn solve_inaccessible(&mut self, moves: &mut Vec<(usize, usize, Placeable)>) -> bool {
-        use BoardState::*;
-
+    fn solve_inaccessible(
+        &mut self,
+        moves: &mut Vec<(usize, usize, Placeable)>,
+    ) -> Result<bool, Contradiction> {
         let mut state_changed = false;
 
-        for row in 0..8u8 {
-            for col in 0..8u8 {
-                if self.board[row as usize][col as usize] == Empty {
-                    let mut empty_count = 0;
-                    let mut beside_path = false;
-                    for offset in NEIGHBORS_4 {
-                        let x = col.wrapping_add_signed(offset.0);
-                        let y = row.wrapping_add_signed(offset.1);
-                        if x < 8 && y < 8 {
-                            if self.board[y as usize][x as usize] == Empty {
-                                empty_count += 1;
-                            } else if self.board[y as usize][x as usize] == Path {
-                                beside_path = true;
-                            }
-                        }
-                    }
-                    if empty_count == 0 && !beside_path {
-                        println!("inaccessible: {col},{row}");
-                        self.board[row as usize][col as usize] = Wall;
-                        state_changed = true;
-                        moves.push((col as usize, row as usize, Placeable::Wall));
-                    }
-                }
-            }
+        let empty = self.board.empty();
+        for (col, row) in (empty & !empty.neighbors() & !self.board.paths.neighbors()).cells() {
+            self.set_state(col, row, BoardState::Wall)?;
+            moves.push((col, row, Placeable::Wall));
+            state_changed = true;
         }
 
-        state_changed
+        Ok(state_changed)
     }
 
     // checks if a region of path has only a single tile through which it can expand
-    fn solve_floodfill(&mut self, moves: &mut Vec<(usize, usize, Placeable)>) -> bool {
-        // TODO: check seed 57387385
-        false
-    }
-
-    /// checks if a path only has one direction it can go
-    fn solve_forced_path(&mut self, moves: &mut Vec<(usize, usize, Placeable)>) -> bool {
+    fn solve_floodfill(
+        &mut self,
+        moves: &mut Vec<(usize, usize, Placeable)>,
+    ) -> Result<bool, Contradiction> {
         use BoardState::*;
+        use ContradictionKind::*;
 
         let mut state_changed = false;
+        let open = self.board.open();
+        let empty = self.board.empty();
 
-        for row in 0..8u8 {
-            for col in 0..8u8 {
-                if self.board[row as usize][col as usize] == Path
-                    && !self.near_chest(col as usize, row as usize)
-                {
-                    let mut empty_cells = vec![];
-                    let mut num_paths = 0;
-                    let mut beside_enemy = false;
-                    let mut beside_treasure = false;
-                    for offset in NEIGHBORS_4 {
-                        let x = col.wrapping_add_signed(offset.0);
-                        let y = row.wrapping_add_signed(offset.1);
-                        if x < 8 && y < 8 {
-                            match self.board[y as usize][x as usize] {
-                                Empty => empty_cells.push((x, y)),
-                                Enemy => beside_enemy = true,
-                                Treasure => beside_treasure = true,
-                                Wall => (),
-                                Path => num_paths += 1,
-                            }
-                        }
-                    }
-                    if empty_cells.len() == 1 && !beside_enemy && !beside_treasure && num_paths == 1
-                    {
-                        let (x, y) = empty_cells[0];
-                        println!("forced path: {col},{row} to {x},{y}");
-                        self.board[y as usize][x as usize] = Path;
+        // split the non-empty open cells into connected regions and find the empty cells around
+        // each of them
+        let mut regions = vec![];
+        let mut unlabelled = open;
+        while let Some((col, row)) = unlabelled.first() {
+            let region = Bitboard::cell(col, row).flood(open);
+            unlabelled &= !region;
+            regions.push(((col, row), region));
+        }
+
+        // a region that has to reach the others through a single cell must use it
+        for &(cell, region) in regions.iter() {
+            if region == open {
+                continue;
+            }
+            let frontier = region.neighbors() & empty;
+            match frontier.count() {
+                0 => return Err(Contradiction::at(cell, WalledOff, "solve_floodfill")),
+                1 => {
+                    // an earlier region may have already opened it
+                    if let Some((x, y)) = (frontier & self.board.empty()).first() {
+                        self.set_state(x, y, Path)?;
+                        moves.push((x, y, Placeable::Path));
                         state_changed = true;
-                        moves.push((x as usize, y as usize, Placeable::Path));
                     }
                 }
+                _ => (),
             }
         }
 
-        state_changed
-    }
+        // empty cells that no region can reach must be walls
+        if !open.is_empty() {
+            let empty = self.board.empty();
+            let reached = self.board.open().flood(self.board.open() | empty);
+            for (col, row) in (empty & !reached).cells() {
+                self.set_state(col, row, Wall)?;
+                moves.push((col, row, Placeable::Wall));
+                state_changed = true;
+            }
+        }
 
-    /// checks 2x2 square for presence of 3 paths
-    fn solve_2x2(&mut self, moves: &mut Vec<(usize, usize, Placeable)>) -> bool {
-        use BoardState::*;
+        Ok(state_changed)
+    }
 
+    /// checks if a path only has one direction it can go
+    fn solve_forced_path(
+        &mut self,
+        moves: &mut Vec<(usize, usize, Placeable)>,
+    ) -> Result<bool, Contradiction> {
         let mut state_changed = false;
 
-        for row in 0..7u8 {
-            for col in 0..7u8 {
-                let mut empty_cells = vec![];
-                let mut path_count = 0;
-                for offset in [(0u8, 0u8), (0, 1), (1, 0), (1, 1)] {
-                    let x = col + offset.0;
-                    let y = row + offset.1;
-                    if self.board[y as usize][x as usize] == Empty {
-                        empty_cells.push((x, y));
-                    } else if self.board[y as usize][x as usize] == Path {
-                        path_count += 1;
-                    }
-                }
-                if path_count == 3 && empty_cells.len() == 1 {
-                    let (x, y) = empty_cells[0];
-                    if !self.near_chest(x as usize, y as usize) {
-                        println!("forced wall (2x2): {x},{y}");
-                        self.board[y as usize][x as usize] = Wall;
-                        state_changed = true;
-                        moves.push((x as usize, y as usize, Placeable::Wall));
-                    }
-                }
+        // anything but an enemy needs two ways in or out
+        let forced =
+            self.board.paths & self.board.empty().one_neighbor() & self.board.open().one_neighbor();
+        for (col, row) in forced.cells() {
+            // a path forced earlier may have opened the same cell
+            let next = Bitboard::cell(col, row).neighbors() & self.board.empty();
+            if let Some((x, y)) = next.first() {
+                self.set_state(x, y, BoardState::Path)?;
+                moves.push((x, y, Placeable::Path));
+                state_changed = true;
             }
         }
-        state_changed
+
+        Ok(state_changed)
     }
 
-    // check if x,y is within 1 tile of a chest
-    fn near_chest(&self, x: usize, y: usize) -> bool {
-        for i in -1..=1 {
-            for j in -1..=1 {
-                let cx = x.wrapping_add_signed(i);
-                let cy = y.wrapping_add_signed(j);
+    /// checks 2x2 square for presence of 3 paths
+    fn solve_2x2(
+        &mut self,
+        moves: &mut Vec<(usize, usize, Placeable)>,
+    ) -> Result<bool, Contradiction> {
+        let mut state_changed = false;
 
-                if cx < 8 && cy < 8 && self.board[cy][cx] == BoardState::Treasure {
-                    println!("found treasure near {x},{y}: {cx},{cy}");
-                    return true;
-                }
+        // blocks that would be entirely open by opening their last empty cell
+        let candidates = (self.board.open() | self.board.empty()).blocks_2x2();
+        for (col, row) in candidates.cells() {
+            let block = Bitboard::block_2x2(col, row);
+            let empty = block & self.board.empty();
+            if (block & self.board.open()).count() == 3
+                && empty.count() == 1
+                && !self.could_be_in_room(block)
+            {
+                let (x, y) = empty.first().unwrap();
+                self.set_state(x, y, BoardState::Wall)?;
+                moves.push((x, y, Placeable::Wall));
+                state_changed = true;
             }
         }
-        false
+        Ok(state_changed)
+    }
+
+    // check if every cell could still lie inside the same treasure room
+    fn could_be_in_room(&self, cells: Bitboard) -> bool {
+        self.treasures.iter().any(|treasure| {
+            treasure.centers().any(|(cx, cy)| {
+                self.is_treasure_room_valid(cx, cy, treasure.x as usize, treasure.y as usize)
+                    && (cells & !Bitboard::block_3x3(cx, cy)).is_empty()
+            })
+        })
     }
 }
 
@@ -671,13 +1403,13 @@ impl Display for Puzzle {
             None => writeln!(f, "     Unseeded"),
         }?;
         write!(f, "   ")?;
-        for i in self.top_counts {
+        for i in self.top_clues {
             write!(f, " {i}")?;
         }
         writeln!(f)?;
         writeln!(f, "    ----------------")?;
-        for (i, row) in self.board.iter().enumerate() {
-            write!(f, " {}|", self.left_counts[i])?;
+        for (i, row) in self.board.to_array().iter().enumerate() {
+            write!(f, " {}|", self.left_clues[i])?;
             for col in row {
                 write!(f, "{col}")?;
             }
@@ -698,3 +1430,885 @@ impl Display for BoardState {
         }
     }
 }
+
+/// Reads either the one-line format written by `Puzzle::serialize` or the grid written by
+/// `Display`. Both round-trip exactly.
+impl FromStr for Puzzle {
+    type Err = ParsePuzzleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim_end_matches(['\r', '\n']);
+        if s.contains('\n') {
+            parse_grid(s)
+        } else {
+            parse_line(s)
+        }
+    }
+}
+
+fn parse_line(line: &str) -> Result<Puzzle, ParsePuzzleError> {
+    use ParsePuzzleError::*;
+
+    // old dumps hold the seed and the board, but not the counts
+    if line.len() == 73 && line.is_ascii() && line.as_bytes()[8] == b' ' {
+        return Err(MissingCounts);
+    }
+    if line.len() != 91 || !line.is_ascii() {
+        return Err(InvalidLength(line.len()));
+    }
+    if [8, 17, 26].iter().any(|&i| line.as_bytes()[i] != b' ') {
+        return Err(InvalidLine(0));
+    }
+
+    let seed = match &line[0..8] {
+        "--------" => None,
+        digits => Some(parse_seed(digits)?),
+    };
+    let top_counts = parse_counts(line[9..17].chars())?;
+    let left_counts = parse_counts(line[18..26].chars())?;
+
+    let mut board = [[BoardState::Empty; 8]; 8];
+    for (i, c) in line[27..].chars().enumerate() {
+        board[i / 8][i % 8] = parse_cell(c)?;
+    }
+
+    Ok(new(top_counts, left_counts, board, seed))
+}
+
+fn parse_grid(grid: &str) -> Result<Puzzle, ParsePuzzleError> {
+    use ParsePuzzleError::*;
+
+    let lines = grid.lines().collect::<Vec<&str>>();
+    if lines.len() != 11 {
+        return Err(InvalidLength(lines.len()));
+    }
+
+    let seed = match lines[0].trim() {
+        "Unseeded" => None,
+        header => match header.strip_prefix("Seed: ") {
+            Some(digits) => Some(parse_seed(digits)?),
+            None => return Err(InvalidLine(0)),
+        },
+    };
+
+    if lines[1].len() != 19 || lines[2].trim() != "----------------" {
+        return Err(InvalidLine(if lines[1].len() != 19 { 1 } else { 2 }));
+    }
+    let top_counts = parse_counts(lines[1].chars().skip(4).step_by(2))?;
+
+    let mut left_counts = [0; 8];
+    let mut board = [[BoardState::Empty; 8]; 8];
+    for row in 0..8 {
+        let line = lines[row + 3];
+        let Some((count, cells)) = line.split_once('|') else {
+            return Err(InvalidLine(row + 3));
+        };
+        if count.len() != 2 || cells.len() != 16 || !cells.is_ascii() {
+            return Err(InvalidLine(row + 3));
+        }
+        left_counts[row] = parse_counts(count[1..].chars())?[0];
+        for (col, c) in cells.chars().skip(1).step_by(2).enumerate() {
+            board[row][col] = parse_cell(c)?;
+        }
+    }
+
+    Ok(new(top_counts, left_counts, board, seed))
+}
+
+fn parse_seed(digits: &str) -> Result<u32, ParsePuzzleError> {
+    match digits.parse::<u32>() {
+        Ok(seed) if seed < SEED_MAX && digits.bytes().all(|c| c.is_ascii_digit()) => Ok(seed),
+        _ => Err(ParsePuzzleError::InvalidSeed(digits.to_string())),
+    }
+}
+
+fn parse_counts(digits: impl Iterator<Item = char>) -> Result<[u8; 8], ParsePuzzleError> {
+    let mut counts = [0; 8];
+    for (count, c) in counts.iter_mut().zip(digits) {
+        match c.to_digit(10) {
+            Some(n) if n < 8 => *count = n as u8,
+            _ => return Err(ParsePuzzleError::InvalidCount(c)),
+        }
+    }
+    Ok(counts)
+}
+
+// the one-line format writes empty cells as spaces, Display as underscores
+fn parse_cell(c: char) -> Result<BoardState, ParsePuzzleError> {
+    match c {
+        ' ' | '_' => Ok(BoardState::Empty),
+        'E' => Ok(BoardState::Enemy),
+        'T' => Ok(BoardState::Treasure),
+        'W' => Ok(BoardState::Wall),
+        'P' => Ok(BoardState::Path),
+        _ => Err(ParsePuzzleError::InvalidCell(c)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Moves = Vec<(usize, usize, Placeable)>;
+
+    // a puzzle with four walls in every row and column, and the given rows of cells
+    fn puzzle(rows: [&str; 8]) -> Puzzle {
+        format!("-------- 44444444 44444444 {}", rows.concat())
+            .parse()
+            .unwrap()
+    }
+
+    // runs a rule and returns the cells it filled in, in the order it placed them
+    fn run(puzzle: &mut Puzzle, solver: Solver) -> Vec<(usize, usize, BoardState)> {
+        let mut moves: Moves = vec![];
+        let changed = solver(puzzle, &mut moves).unwrap();
+        assert_eq!(changed, !moves.is_empty());
+        assert_counters(puzzle);
+        moves
+            .into_iter()
+            .map(|(x, y, placed)| {
+                let state = puzzle.board.get(x, y);
+                match placed {
+                    Placeable::Wall => assert_eq!(state, BoardState::Wall),
+                    Placeable::Path => assert_eq!(state, BoardState::Path),
+                }
+                (x, y, state)
+            })
+            .collect()
+    }
+
+    // the counters kept by set_state agree with the cells on the board
+    fn assert_counters(puzzle: &Puzzle) {
+        let board = puzzle.get_board();
+        for i in 0..8 {
+            let row_walls = board[i].iter().filter(|&&s| s == BoardState::Wall).count();
+            let col_walls = board
+                .iter()
+                .filter(|row| row[i] == BoardState::Wall)
+                .count();
+            assert_eq!(puzzle.left_counts[i] as usize, 4 - row_walls, "row {i}");
+            assert_eq!(puzzle.top_counts[i] as usize, 4 - col_walls, "column {i}");
+
+            let row_empty = board[i].iter().filter(|&&s| s == BoardState::Empty).count();
+            let col_empty = board
+                .iter()
+                .filter(|row| row[i] == BoardState::Empty)
+                .count();
+            assert_eq!(puzzle.empty_count_row(i) as usize, row_empty, "row {i}");
+            assert_eq!(puzzle.empty_count_col(i) as usize, col_empty, "column {i}");
+        }
+    }
+
+    // a filled board, with wall counts taken from its walls
+    fn filled(rows: [&str; 8]) -> Puzzle {
+        let board = rows.map(|row| {
+            let cells = row.chars().map(|c| parse_cell(c).unwrap());
+            <[BoardState; 8]>::try_from(cells.collect::<Vec<_>>()).unwrap()
+        });
+        let walls =
+            |cells: [BoardState; 8]| cells.iter().filter(|&&s| s == BoardState::Wall).count() as u8;
+        let top = std::array::from_fn(|col| walls(board.map(|row| row[col])));
+        let left = board.map(walls);
+        new(top, left, board, None)
+    }
+
+    // a valid solution with a treasure room, whose exit is at 1,5
+    #[rustfmt::skip]
+    const SOLVED: [&str; 8] = [
+        "EWWWWWWW",
+        "PWWWWWWW",
+        "PWWWWWEW",
+        "PWPPPWPP",
+        "PWPPPWWP",
+        "PPPPTWWP",
+        "WPWWWEWP",
+        "EPPPPPPP",
+    ];
+
+    // the solution with one cell changed
+    fn solved_with(x: usize, y: usize, c: char) -> Puzzle {
+        let mut rows = SOLVED.map(String::from);
+        rows[y].replace_range(x..x + 1, &c.to_string());
+        filled(std::array::from_fn(|i| rows[i].as_str()))
+    }
+
+    #[test]
+    fn solved_board_is_valid() {
+        assert_eq!(filled(SOLVED).validate(), Ok(()));
+    }
+
+    #[test]
+    fn empty_cell_is_reported() {
+        let violations = solved_with(7, 7, '_').validate().unwrap_err();
+        assert!(violations.contains(&RuleViolation::EmptyCell { x: 7, y: 7 }));
+    }
+
+    #[test]
+    fn wrong_wall_counts_are_reported() {
+        let solved = filled(SOLVED);
+        let mut top = solved.get_top_clues();
+        let mut left = solved.get_left_clues();
+        top[3] -= 1;
+        left[0] -= 1;
+        let puzzle = new(top, left, solved.get_board(), None);
+        assert_eq!(
+            puzzle.validate(),
+            Err(vec![
+                RuleViolation::RowWallCount {
+                    row: 0,
+                    expected: 6,
+                    found: 7
+                },
+                RuleViolation::ColumnWallCount {
+                    col: 3,
+                    expected: 3,
+                    found: 4
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn wide_hallway_is_reported() {
+        let puzzle = solved_with(6, 4, 'P');
+        assert_eq!(
+            puzzle.validate(),
+            Err(vec![RuleViolation::WideHallway { x: 6, y: 3 }])
+        );
+    }
+
+    #[test]
+    fn dead_end_without_enemy_is_reported() {
+        let puzzle = solved_with(0, 0, 'P');
+        assert_eq!(
+            puzzle.validate(),
+            Err(vec![RuleViolation::DeadEndWithoutEnemy { x: 0, y: 0 }])
+        );
+    }
+
+    #[test]
+    fn enemy_outside_dead_end_is_reported() {
+        let puzzle = solved_with(3, 7, 'E');
+        assert_eq!(
+            puzzle.validate(),
+            Err(vec![RuleViolation::EnemyNotInDeadEnd { x: 3, y: 7 }])
+        );
+    }
+
+    #[test]
+    fn treasure_without_room_is_reported() {
+        // a wall in the middle of the room
+        let puzzle = solved_with(3, 4, 'W');
+        assert_eq!(
+            puzzle.validate(),
+            Err(vec![RuleViolation::TreasureWithoutRoom { x: 4, y: 5 }])
+        );
+    }
+
+    #[test]
+    fn disconnected_path_is_reported() {
+        // the enemy at 6,2 only reaches the rest through 6,3
+        let puzzle = solved_with(6, 3, 'W');
+        let violations = puzzle.validate().unwrap_err();
+        assert!(violations.contains(&RuleViolation::Disconnected { x: 6, y: 2 }));
+    }
+
+    // the givens of `SOLVED`, which has no other solution
+    const UNIQUE: &str =
+        "-------- 15444653 77623250 E                     E                     T        E  E       ";
+
+    // the rules stall before this one is solved, so solving it needs a guess
+    const STALLS: &str =
+        "-------- 23363647 67642441  E                          E E E                     E E      E";
+
+    // two solutions, with walls at 3,6 and 5,7 in one and at 5,6 and 3,7 in the other
+    const AMBIGUOUS: &str =
+        "-------- 54245404 66333331      E         E     E         E   T E  E   E  E               E";
+
+    #[test]
+    fn unique_puzzle_has_one_solution() {
+        let puzzle = UNIQUE.parse::<Puzzle>().unwrap();
+        let solutions = puzzle.count_solutions(2);
+        assert_eq!(solutions.len(), 1);
+        assert_eq!(solutions[0].get_board(), filled(SOLVED).get_board());
+    }
+
+    #[test]
+    fn ambiguous_puzzle_has_two_solutions() {
+        let puzzle = AMBIGUOUS.parse::<Puzzle>().unwrap();
+        let solutions = puzzle.count_solutions(2);
+        assert_eq!(solutions.len(), 2);
+        for solution in solutions.iter() {
+            assert_eq!(solution.validate(), Ok(()));
+        }
+        assert_eq!(
+            solutions[0].differences(&solutions[1]),
+            [(3, 6), (5, 6), (3, 7), (5, 7)]
+        );
+        // there are no more to find past the limit
+        assert_eq!(puzzle.count_solutions(10).len(), 2);
+    }
+
+    #[test]
+    fn count_solutions_stops_at_limit() {
+        let puzzle = AMBIGUOUS.parse::<Puzzle>().unwrap();
+        assert_eq!(puzzle.count_solutions(1).len(), 1);
+        assert!(puzzle.count_solutions(0).is_empty());
+    }
+
+    // both formats read back to the puzzle they were written from
+    fn assert_round_trips(puzzle: &Puzzle) {
+        let line = puzzle.serialize();
+        let parsed = line.parse::<Puzzle>().unwrap();
+        assert_eq!(parsed.serialize(), line);
+        assert_eq!(parsed.get_seed(), puzzle.get_seed());
+
+        let grid = puzzle.to_string();
+        let parsed = grid.parse::<Puzzle>().unwrap();
+        assert_eq!(parsed.to_string(), grid);
+        assert_eq!(parsed.serialize(), line);
+    }
+
+    #[test]
+    fn puzzles_round_trip() {
+        let unique = UNIQUE.parse::<Puzzle>().unwrap();
+        assert_round_trips(&unique);
+        assert_round_trips(&filled(SOLVED));
+        for seed in [0, 42, 23452480] {
+            let seeded = new(
+                unique.get_top_clues(),
+                unique.get_left_clues(),
+                unique.get_board(),
+                Some(seed),
+            );
+            assert_round_trips(&seeded);
+        }
+    }
+
+    #[test]
+    fn small_seeds_keep_their_leading_zeros() {
+        let line = UNIQUE.replacen("--------", "00000042", 1);
+        let puzzle = line.parse::<Puzzle>().unwrap();
+        assert_eq!(puzzle.get_seed(), Some(42));
+        assert_eq!(puzzle.serialize(), line);
+        assert!(puzzle.to_string().starts_with("    Seed: 42\n"));
+    }
+
+    #[test]
+    fn malformed_lines_are_rejected() {
+        use ParsePuzzleError::*;
+        let with = |i: usize, c: &str| {
+            let mut line = UNIQUE.to_string();
+            line.replace_range(i..i + 1, c);
+            line.parse::<Puzzle>().err()
+        };
+
+        assert_eq!(
+            UNIQUE[..90].parse::<Puzzle>().err(),
+            Some(InvalidLength(90))
+        );
+        let dump = format!("00000042 {}", &UNIQUE[27..]);
+        assert_eq!(dump.parse::<Puzzle>().err(), Some(MissingCounts));
+        assert_eq!(with(8, "-"), Some(InvalidLine(0)));
+        assert_eq!(with(0, "0"), Some(InvalidSeed("0-------".to_string())));
+        assert_eq!(with(9, "8"), Some(InvalidCount('8')));
+        assert_eq!(with(20, "x"), Some(InvalidCount('x')));
+        assert_eq!(with(30, "X"), Some(InvalidCell('X')));
+    }
+
+    #[test]
+    fn malformed_grids_are_rejected() {
+        use ParsePuzzleError::*;
+        let grid = filled(SOLVED).to_string();
+        let with = |line: usize, text: &str| {
+            let mut lines = grid.lines().collect::<Vec<&str>>();
+            lines[line] = text;
+            lines.join("\n").parse::<Puzzle>().err()
+        };
+
+        let short = grid.lines().take(10).collect::<Vec<&str>>().join("\n");
+        assert_eq!(short.parse::<Puzzle>().err(), Some(InvalidLength(10)));
+        assert_eq!(with(0, "    Seed 42"), Some(InvalidLine(0)));
+        assert_eq!(with(0, "    Seed: 4x"), Some(InvalidSeed("4x".to_string())));
+        assert_eq!(with(1, "    7 7 6 2"), Some(InvalidLine(1)));
+        assert_eq!(with(2, "    ========"), Some(InvalidLine(2)));
+        assert_eq!(with(1, "    7 7 6 2 3 2 5 9"), Some(InvalidCount('9')));
+        assert_eq!(with(4, " 7 E W W W W W W W"), Some(InvalidLine(4)));
+        assert_eq!(with(4, " 7| E W W W W W W ?"), Some(InvalidCell('?')));
+    }
+
+    #[test]
+    fn guessing_scores_harder_than_propagation() {
+        let mut easy = UNIQUE.parse::<Puzzle>().unwrap();
+        let easy = easy.solve().unwrap();
+        assert!(!easy.backtracked);
+
+        let mut hard = STALLS.parse::<Puzzle>().unwrap();
+        let hard = hard.solve().unwrap();
+        assert!(hard.backtracked);
+
+        assert!(hard.difficulty() > easy.difficulty());
+    }
+
+    #[test]
+    fn inaccessible_cell_becomes_wall() {
+        #[rustfmt::skip]
+        let mut puzzle = puzzle([
+            "_W______",
+            "W_______",
+            "________",
+            "________",
+            "________",
+            "______WP",
+            "_____W__",
+            "________",
+        ]);
+        // 7,6 is cut off from the other empty cells but still reachable from the path
+        let moves = run(&mut puzzle, Puzzle::solve_inaccessible);
+        assert_eq!(moves, [(0, 0, BoardState::Wall)]);
+    }
+
+    #[test]
+    fn path_with_one_way_on_is_extended() {
+        #[rustfmt::skip]
+        let mut puzzle = puzzle([
+            "PPW_____",
+            "_W______",
+            "P_______",
+            "________",
+            "___W____",
+            "__WP____",
+            "________",
+            "________",
+        ]);
+        // 3,5 has no open neighbour yet, so it could still be the end of a corridor
+        let moves = run(&mut puzzle, Puzzle::solve_forced_path);
+        assert_eq!(moves, [(0, 1, BoardState::Path)]);
+    }
+
+    #[test]
+    fn path_beside_enemy_is_extended() {
+        #[rustfmt::skip]
+        let mut puzzle = puzzle([
+            "EPW_____",
+            "W_______",
+            "________",
+            "________",
+            "________",
+            "________",
+            "________",
+            "________",
+        ]);
+        let moves = run(&mut puzzle, Puzzle::solve_forced_path);
+        assert_eq!(moves, [(1, 1, BoardState::Path)]);
+    }
+
+    #[test]
+    fn enemy_with_one_way_out_gets_path() {
+        #[rustfmt::skip]
+        let mut puzzle = puzzle([
+            "EW______",
+            "________",
+            "________",
+            "____W___",
+            "___PEW__",
+            "____W___",
+            "________",
+            "________",
+        ]);
+        let moves = run(&mut puzzle, Puzzle::solve_enemies);
+        assert_eq!(moves, [(0, 1, BoardState::Path)]);
+    }
+
+    #[test]
+    fn enemy_with_path_is_walled_in() {
+        #[rustfmt::skip]
+        let mut puzzle = puzzle([
+            "________",
+            "________",
+            "___P____",
+            "___E____",
+            "________",
+            "________",
+            "________",
+            "________",
+        ]);
+        let moves = run(&mut puzzle, Puzzle::solve_enemies);
+        assert_eq!(
+            moves,
+            [
+                (2, 3, BoardState::Wall),
+                (4, 3, BoardState::Wall),
+                (3, 4, BoardState::Wall),
+            ]
+        );
+    }
+
+    #[test]
+    fn adjacent_enemies_are_a_contradiction() {
+        #[rustfmt::skip]
+        let mut puzzle = puzzle([
+            "EE______",
+            "________",
+            "________",
+            "________",
+            "________",
+            "________",
+            "________",
+            "________",
+        ]);
+        let result = Puzzle::solve_enemies(&mut puzzle, &mut vec![]);
+        assert!(matches!(
+            result,
+            Err(Contradiction {
+                location: Location::Cell(0, 0),
+                kind: ContradictionKind::AdjacentEnemies,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn dead_end_becomes_wall() {
+        #[rustfmt::skip]
+        let mut puzzle = puzzle([
+            "_W______",
+            "________",
+            "________",
+            "___W____",
+            "__W_W___",
+            "________",
+            "________",
+            "________",
+        ]);
+        // 0,0 is in the corner with a wall beside it, and 3,4 is walled in on three sides
+        let moves = run(&mut puzzle, Puzzle::solve_deadend);
+        assert_eq!(moves, [(0, 0, BoardState::Wall), (3, 4, BoardState::Wall)]);
+    }
+
+    #[test]
+    fn path_in_corner_turns() {
+        #[rustfmt::skip]
+        let mut puzzle = puzzle([
+            "P_______",
+            "________",
+            "________",
+            "___W____",
+            "__WP____",
+            "________",
+            "________",
+            "________",
+        ]);
+        let moves = run(&mut puzzle, Puzzle::solve_corners);
+        assert_eq!(
+            moves,
+            [
+                (1, 0, BoardState::Path),
+                (0, 1, BoardState::Path),
+                (4, 4, BoardState::Path),
+                (3, 5, BoardState::Path),
+            ]
+        );
+    }
+
+    #[test]
+    fn region_with_one_way_out_takes_it() {
+        #[rustfmt::skip]
+        let mut puzzle = puzzle([
+            "PW______",
+            "________",
+            "________",
+            "________",
+            "________",
+            "_____P__",
+            "________",
+            "________",
+        ]);
+        // the path at 0,0 can only reach the one at 5,5 through 0,1
+        let moves = run(&mut puzzle, Puzzle::solve_floodfill);
+        assert_eq!(moves, [(0, 1, BoardState::Path)]);
+    }
+
+    #[test]
+    fn unreachable_cell_becomes_wall() {
+        #[rustfmt::skip]
+        let mut puzzle = puzzle([
+            "P_______",
+            "________",
+            "________",
+            "________",
+            "________",
+            "________",
+            "_______W",
+            "______W_",
+        ]);
+        let moves = run(&mut puzzle, Puzzle::solve_floodfill);
+        assert_eq!(moves, [(7, 7, BoardState::Wall)]);
+    }
+
+    #[test]
+    fn regions_with_several_ways_out_are_left_alone() {
+        #[rustfmt::skip]
+        let mut puzzle = puzzle([
+            "P_______",
+            "________",
+            "________",
+            "________",
+            "________",
+            "_____P__",
+            "________",
+            "_______P",
+        ]);
+        // the paths in the corners each have two ways out
+        let moves = run(&mut puzzle, Puzzle::solve_floodfill);
+        assert_eq!(moves, []);
+    }
+
+    #[test]
+    fn single_region_with_one_way_out_is_left_alone() {
+        #[rustfmt::skip]
+        let mut puzzle = puzzle([
+            "PW______",
+            "________",
+            "________",
+            "________",
+            "________",
+            "________",
+            "________",
+            "________",
+        ]);
+        // with nothing else open, the path has no other region to reach
+        let moves = run(&mut puzzle, Puzzle::solve_floodfill);
+        assert_eq!(moves, []);
+    }
+
+    #[test]
+    fn walled_off_region_is_a_contradiction() {
+        #[rustfmt::skip]
+        let mut puzzle = puzzle([
+            "PW______",
+            "W_______",
+            "________",
+            "________",
+            "________",
+            "_____P__",
+            "________",
+            "________",
+        ]);
+        let result = Puzzle::solve_floodfill(&mut puzzle, &mut vec![]);
+        assert!(matches!(
+            result,
+            Err(Contradiction {
+                location: Location::Cell(0, 0),
+                kind: ContradictionKind::WalledOff,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn treasure_exit_is_narrowed_by_wall_counts() {
+        #[rustfmt::skip]
+        let mut puzzle = puzzle([
+            "PPP_____",
+            "PTP_WWWW",
+            "PPP_____",
+            "________",
+            "________",
+            "___W____",
+            "___W____",
+            "________",
+        ]);
+        // row 1 has all of its walls, and column 3 only has room for two more, so the exit is
+        // 3,1 and the rest of the boundary is walled off
+        let moves = run(&mut puzzle, Puzzle::solve_treasures);
+        assert_eq!(
+            moves,
+            [
+                (3, 1, BoardState::Path),
+                (3, 0, BoardState::Wall),
+                (3, 2, BoardState::Wall),
+                (0, 3, BoardState::Wall),
+                (1, 3, BoardState::Wall),
+                (2, 3, BoardState::Wall),
+            ]
+        );
+    }
+
+    #[test]
+    fn treasure_exit_is_left_open_when_counts_allow_several() {
+        #[rustfmt::skip]
+        let mut puzzle = puzzle([
+            "PPP_____",
+            "PTP_____",
+            "PPP_____",
+            "________",
+            "________",
+            "________",
+            "________",
+            "________",
+        ]);
+        let moves = run(&mut puzzle, Puzzle::solve_treasures);
+        assert_eq!(moves, []);
+    }
+
+    #[test]
+    fn treasure_exit_walls_off_cells_that_cant_be_the_exit() {
+        #[rustfmt::skip]
+        let mut puzzle = puzzle([
+            "PPP_____",
+            "PTP_____",
+            "PPP_____",
+            "________",
+            "________",
+            "___W____",
+            "___W____",
+            "________",
+        ]);
+        // column 3 only has room for two more walls, so the exit is one of its three cells and
+        // the ones below the room are walls
+        let moves = run(&mut puzzle, Puzzle::solve_treasures);
+        assert_eq!(
+            moves,
+            [
+                (0, 3, BoardState::Wall),
+                (1, 3, BoardState::Wall),
+                (2, 3, BoardState::Wall),
+            ]
+        );
+    }
+
+    #[test]
+    fn hint_after_rules_stall_comes_from_search() {
+        let mut puzzle = STALLS.parse::<Puzzle>().unwrap();
+        puzzle.propagate(&mut SolveReport::default()).unwrap();
+        let hint = puzzle.next_hint().unwrap().unwrap();
+        assert!(matches!(hint.source, HintSource::Search { .. }));
+
+        let mut solution = puzzle.clone();
+        solution.solve().unwrap();
+        let expected = match hint.placeable {
+            Placeable::Wall => BoardState::Wall,
+            Placeable::Path => BoardState::Path,
+        };
+        assert_eq!(solution.board.get(hint.x, hint.y), expected);
+    }
+
+    // the first hint for a puzzle, which should come from the treasure rule
+    fn treasure_hint(puzzle: &Puzzle) -> Hint {
+        let hint = puzzle.next_hint().unwrap().unwrap();
+        assert_eq!(hint.source, HintSource::Rule("solve_treasures"));
+        hint
+    }
+
+    #[test]
+    fn treasure_hint_names_the_room_covering_the_cell() {
+        // 2,1 is as close to the treasure at 4,0 as to the one at 1,1, but only the rooms left
+        // for 1,1 all cover it
+        let line = "47468067 33351435 52246152     TP  PT  PP      PP                        E  E        E     ";
+        let hint = treasure_hint(&line.parse().unwrap());
+        assert_eq!((hint.x, hint.y), (2, 1));
+        assert!(matches!(hint.placeable, Placeable::Path));
+        assert_eq!(hint.cells, [(1, 1)]);
+        assert_eq!(
+            hint.explanation,
+            "every possible room for the treasure at 1,1 covers this cell"
+        );
+    }
+
+    #[test]
+    fn treasure_hint_explains_the_only_exit() {
+        let line = "-------- 56415455 66356324 WWWPEWWWEWWPWWWWPWPPPEWWPPPWWWWWWW P WWWWW P PPPWEPPWPPPWWWEWPTP";
+        let hint = treasure_hint(&line.parse().unwrap());
+        assert_eq!((hint.x, hint.y), (4, 5));
+        assert!(matches!(hint.placeable, Placeable::Path));
+        assert_eq!(hint.cells, [(6, 7)]);
+        assert_eq!(
+            hint.explanation,
+            "this is the only exit left for the room around the treasure at 6,7"
+        );
+    }
+
+    #[test]
+    fn treasure_hint_explains_walls_around_the_exit() {
+        // three walls to a line, so the row holding the exit isn't filled in by solve_trivial
+        #[rustfmt::skip]
+        let rows = [
+            "TPP_____",
+            "PPPP____",
+            "PPP_____",
+            "________",
+            "________",
+            "________",
+            "________",
+            "________",
+        ];
+        let puzzle = format!("-------- 33333333 33333333 {}", rows.concat())
+            .parse()
+            .unwrap();
+        // the room's exit is already at 3,1, so the rest of its boundary is walled off
+        let hint = treasure_hint(&puzzle);
+        assert_eq!((hint.x, hint.y), (3, 0));
+        assert!(matches!(hint.placeable, Placeable::Wall));
+        assert_eq!(hint.cells, [(0, 0)]);
+        assert_eq!(
+            hint.explanation,
+            "the room around the treasure at 0,0 can only have one exit"
+        );
+    }
+
+    #[test]
+    fn open_2x2_outside_rooms_gets_wall() {
+        #[rustfmt::skip]
+        let mut puzzle = puzzle([
+            "________",
+            "________",
+            "________",
+            "___PP___",
+            "___P____",
+            "________",
+            "________",
+            "________",
+        ]);
+        let moves = run(&mut puzzle, Puzzle::solve_2x2);
+        assert_eq!(moves, [(4, 4, BoardState::Wall)]);
+    }
+
+    #[test]
+    fn open_2x2_inside_treasure_room_is_left_open() {
+        #[rustfmt::skip]
+        let mut puzzle = puzzle([
+            "________",
+            "_T______",
+            "PP______",
+            "P_______",
+            "________",
+            "________",
+            "________",
+            "________",
+        ]);
+        // a room centred on 1,2 holds the whole block
+        let moves = run(&mut puzzle, Puzzle::solve_2x2);
+        assert_eq!(moves, []);
+    }
+
+    #[test]
+    fn open_2x2_beside_treasure_but_outside_its_rooms_gets_wall() {
+        #[rustfmt::skip]
+        let mut puzzle = puzzle([
+            "________",
+            "_T_W____",
+            "__PP____",
+            "__P_____",
+            "________",
+            "________",
+            "________",
+            "________",
+        ]);
+        // the wall at 3,1 rules out the rooms centred on 2,1 and 2,2, and the rooms left don't
+        // reach column 3. The block touches the treasure, which used to be taken as being inside
+        // its room.
+        let moves = run(&mut puzzle, Puzzle::solve_2x2);
+        assert_eq!(moves, [(3, 3, BoardState::Wall)]);
+    }
+}
@@ -0,0 +1,218 @@
+use std::path::Path;
+
+use image::{imageops, ImageResult, Rgba, RgbaImage};
+
+use crate::{
+    puzzle::{BoardState, Puzzle},
+    vision::{self, GAME_SIZE, SEED_GLYPHS},
+    TileContents, BOARD_BASE, LEFT_NUMS_BASE, LEFT_NUMS_OFFSETS, SEED_BASE, TILE_SIZE,
+    TOP_NUMS_BASE, TOP_NUMS_OFFSETS,
+};
+
+// the empty board on the left of this screenshot is used as the backdrop
+const BACKGROUND: &[u8] = include_bytes!("../dungeon_empty.png");
+
+// there is no red 0, the game always shows it in grey
+const RED_DIGITS: [&[u8]; 8] = [
+    include_bytes!("../nums/grey_0.png"),
+    include_bytes!("../nums/red_1.png"),
+    include_bytes!("../nums/red_2.png"),
+    include_bytes!("../nums/red_3.png"),
+    include_bytes!("../nums/red_4.png"),
+    include_bytes!("../nums/red_5.png"),
+    include_bytes!("../nums/red_6.png"),
+    include_bytes!("../nums/red_7.png"),
+];
+const GREY_DIGITS: [&[u8]; 8] = [
+    include_bytes!("../nums/grey_0.png"),
+    include_bytes!("../nums/grey_1.png"),
+    include_bytes!("../nums/grey_2.png"),
+    include_bytes!("../nums/grey_3.png"),
+    include_bytes!("../nums/grey_4.png"),
+    include_bytes!("../nums/grey_5.png"),
+    include_bytes!("../nums/grey_6.png"),
+    include_bytes!("../nums/grey_7.png"),
+];
+// drawn for enemies when no monster is given for their tile, or the monster has no sprite
+const DEFAULT_MONSTER: TileContents = TileContents::Skeleton;
+
+// colours of the stone frame around the board, reused for walls and the seed box
+const STONE: Rgba<u8> = Rgba([122, 90, 78, 255]);
+const STONE_LIGHT: Rgba<u8> = Rgba([172, 122, 101, 255]);
+const STONE_DARK: Rgba<u8> = Rgba([52, 19, 5, 255]);
+
+// interior of the seed box, and where the first digit starts in it
+const SEED_BOX: (u32, u32, u32, u32) = (101, 102, 74, 10);
+const SEED_INDENT: u32 = 4;
+
+impl Puzzle {
+    /// Draws the puzzle the way the game shows it. With `with_solution`, the walls of the
+    /// solution are drawn as well; a puzzle that can't be solved is drawn as it stands. The
+    /// puzzle doesn't know which monster sits on a tile, so every enemy is drawn as a skeleton.
+    pub fn render(&self, with_solution: bool) -> RgbaImage {
+        self.render_with_monsters(with_solution, &[[DEFAULT_MONSTER; 8]; 8])
+    }
+
+    /// Draws the puzzle like `render`, with the enemy on each tile drawn as the monster read
+    /// there, e.g. by `vision::parse_tiles`. Monsters without a captured sprite are drawn as
+    /// skeletons.
+    pub fn render_with_monsters(
+        &self,
+        with_solution: bool,
+        monsters: &[[TileContents; 8]; 8],
+    ) -> RgbaImage {
+        let mut puzzle = self.clone();
+        if with_solution && puzzle.solve().is_err() {
+            puzzle = self.clone();
+        }
+        let board = puzzle.get_board();
+
+        let mut image = load(BACKGROUND);
+        image = imageops::crop(&mut image, 0, 0, GAME_SIZE.0, GAME_SIZE.1).to_image();
+
+        // counts turn grey once their row or column has all of its walls
+        let top_clues = puzzle.get_top_clues();
+        let left_clues = puzzle.get_left_clues();
+        for i in 0..8 {
+            let walls = board
+                .iter()
+                .filter(|row| row[i] == BoardState::Wall)
+                .count() as u8;
+            let x = TOP_NUMS_BASE.0 + TOP_NUMS_OFFSETS[i] + TILE_SIZE * i;
+            draw_tile(&mut image, &digit(top_clues[i], walls), x, TOP_NUMS_BASE.1);
+
+            let walls = board[i].iter().filter(|&&s| s == BoardState::Wall).count() as u8;
+            let y = LEFT_NUMS_BASE.1 + LEFT_NUMS_OFFSETS[i] + TILE_SIZE * i;
+            draw_tile(
+                &mut image,
+                &digit(left_clues[i], walls),
+                LEFT_NUMS_BASE.0,
+                y,
+            );
+        }
+
+        let treasure = sprite(TileContents::Chest);
+        for (row, states) in board.iter().enumerate() {
+            for (col, state) in states.iter().enumerate() {
+                let x = BOARD_BASE.0 + col * TILE_SIZE;
+                let y = BOARD_BASE.1 + row * TILE_SIZE;
+                match state {
+                    BoardState::Enemy => {
+                        let monster = match monsters[row][col] {
+                            TileContents::Empty | TileContents::Chest => DEFAULT_MONSTER,
+                            monster => monster,
+                        };
+                        draw_tile(&mut image, &sprite(monster), x, y)
+                    }
+                    BoardState::Treasure => draw_tile(&mut image, &treasure, x, y),
+                    BoardState::Wall => draw_wall(&mut image, x as u32, y as u32),
+                    BoardState::Path | BoardState::Empty => (),
+                }
+            }
+        }
+
+        draw_seed(&mut image, puzzle.get_seed());
+        image
+    }
+
+    pub fn render_png(&self, path: impl AsRef<Path>, with_solution: bool) -> ImageResult<()> {
+        self.render(with_solution).save(path)
+    }
+}
+
+fn load(bytes: &[u8]) -> RgbaImage {
+    image::load_from_memory(bytes)
+        .expect("Bundled image is invalid")
+        .to_rgba8()
+}
+
+fn sprite(contents: TileContents) -> RgbaImage {
+    load(
+        vision::sprite(contents).unwrap_or_else(|| {
+            vision::sprite(DEFAULT_MONSTER).expect("Default monster has no sprite")
+        }),
+    )
+}
+
+fn digit(count: u8, walls: u8) -> RgbaImage {
+    if walls == count {
+        load(GREY_DIGITS[count as usize])
+    } else {
+        load(RED_DIGITS[count as usize])
+    }
+}
+
+fn draw_tile(image: &mut RgbaImage, tile: &RgbaImage, x: usize, y: usize) {
+    imageops::replace(image, tile, x as i64, y as i64);
+}
+
+// a raised block of stone filling the tile, lit from the top left
+fn draw_wall(image: &mut RgbaImage, x: u32, y: u32) {
+    let size = TILE_SIZE as u32;
+    for dy in 1..size - 1 {
+        for dx in 1..size - 1 {
+            let color = if dx < 3 || dy < 3 {
+                STONE_LIGHT
+            } else if dx > size - 4 || dy > size - 4 {
+                STONE_DARK
+            } else {
+                STONE
+            };
+            image.put_pixel(x + dx, y + dy, color);
+        }
+    }
+}
+
+fn draw_seed(image: &mut RgbaImage, seed: Option<u32>) {
+    let (bx, by, width, height) = SEED_BOX;
+    for y in by..by + height {
+        for x in bx..bx + width {
+            image.put_pixel(x, y, STONE);
+        }
+    }
+
+    let Some(seed) = seed else {
+        return;
+    };
+    // digits are as wide as their glyph, with a column of space between them
+    let mut x = SEED_BASE.0 as u32 + SEED_INDENT;
+    let y = SEED_BASE.1 as u32;
+    for c in format!("{seed:08}").bytes() {
+        let glyph = SEED_GLYPHS[(c - b'0') as usize];
+        for (dy, line) in glyph.iter().enumerate() {
+            for (dx, pixel) in line.bytes().enumerate() {
+                if pixel == b'#' {
+                    image.put_pixel(x + dx as u32, y + dy as u32, STONE_DARK);
+                }
+            }
+        }
+        x += glyph[0].len() as u32 + 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{calibration::Calibration, puzzle, vision};
+
+    use super::*;
+
+    fn seeded(seed: Option<u32>) -> Puzzle {
+        puzzle::new([0; 8], [0; 8], [[BoardState::Empty; 8]; 8], seed)
+    }
+
+    #[test]
+    fn rendered_seed_reads_back() {
+        for seed in [23452480, 93027184, 56570157, 42, 99999998] {
+            let image = seeded(Some(seed)).render(false);
+            let read = vision::parse_seed(&image, &Calibration::DEFAULT);
+            assert_eq!(read, Ok(Some(seed)), "seed {seed:08}");
+        }
+    }
+
+    #[test]
+    fn unseeded_puzzle_renders_empty_seed_box() {
+        let image = seeded(None).render(false);
+        let read = vision::parse_seed(&image, &Calibration::DEFAULT);
+        assert_eq!(read, Ok(None));
+    }
+}
@@ -0,0 +1,208 @@
+//! Vector drawings of puzzles for printing. Everything is laid out in board units, where a cell
+//! is 10 units wide, and scaled to millimetres on the page.
+
+use std::fmt::Write;
+
+use crate::puzzle::{BoardState, Puzzle};
+
+const CELL: f32 = 10.0;
+// the counts take one cell above and to the left of the board, the caption sits below it
+const PUZZLE_WIDTH: f32 = 9.0 * CELL;
+const PUZZLE_HEIGHT: f32 = 9.0 * CELL + 8.0;
+
+const A4: (f32, f32) = (210.0, 297.0);
+const PAGE_MARGIN: f32 = 15.0;
+const PUZZLES_PER_ROW: usize = 2;
+pub const PUZZLES_PER_PAGE: usize = 4;
+
+const INK: &str = "#000";
+const WALL_FILL: &str = "#999";
+
+impl Puzzle {
+    /// A standalone SVG of the puzzle, `width_mm` wide. With `with_solution`, the walls of the
+    /// solution are shaded in; a puzzle that can't be solved is drawn as it stands.
+    pub fn to_svg(&self, with_solution: bool, width_mm: f32) -> String {
+        let height_mm = width_mm * PUZZLE_HEIGHT / PUZZLE_WIDTH;
+        let mut svg = header(width_mm, height_mm, PUZZLE_WIDTH, PUZZLE_HEIGHT);
+        draw_puzzle(&mut svg, self, with_solution);
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+/// Lays the puzzles out on A4 pages, 4 to a page with their seeds as captions
+pub fn pages(puzzles: &[Puzzle], with_solution: bool) -> Vec<String> {
+    puzzles
+        .chunks(PUZZLES_PER_PAGE)
+        .map(|chunk| page(chunk, with_solution))
+        .collect()
+}
+
+fn page(puzzles: &[Puzzle], with_solution: bool) -> String {
+    let rows = PUZZLES_PER_PAGE / PUZZLES_PER_ROW;
+    let slot_width = (A4.0 - 2.0 * PAGE_MARGIN) / PUZZLES_PER_ROW as f32;
+    let slot_height = (A4.1 - 2.0 * PAGE_MARGIN) / rows as f32;
+    // leave a gutter between neighbouring puzzles
+    let scale = (slot_width * 0.9 / PUZZLE_WIDTH).min(slot_height * 0.9 / PUZZLE_HEIGHT);
+
+    let mut svg = header(A4.0, A4.1, A4.0, A4.1);
+    for (i, puzzle) in puzzles.iter().enumerate() {
+        let col = (i % PUZZLES_PER_ROW) as f32;
+        let row = (i / PUZZLES_PER_ROW) as f32;
+        let x = PAGE_MARGIN + col * slot_width + (slot_width - PUZZLE_WIDTH * scale) / 2.0;
+        let y = PAGE_MARGIN + row * slot_height + (slot_height - PUZZLE_HEIGHT * scale) / 2.0;
+        writeln!(
+            svg,
+            r#"<g transform="translate({x:.2} {y:.2}) scale({scale:.4})">"#
+        )
+        .unwrap();
+        draw_puzzle(&mut svg, puzzle, with_solution);
+        svg.push_str("</g>\n");
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn header(width_mm: f32, height_mm: f32, view_width: f32, view_height: f32) -> String {
+    format!(
+        concat!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.2}mm" height="{:.2}mm" "#,
+            r#"viewBox="0 0 {} {}" font-family="sans-serif" text-anchor="middle">"#,
+            "\n"
+        ),
+        width_mm, height_mm, view_width, view_height
+    )
+}
+
+fn draw_puzzle(svg: &mut String, puzzle: &Puzzle, with_solution: bool) {
+    let mut puzzle = puzzle.clone();
+    if with_solution {
+        let unsolved = puzzle.clone();
+        if puzzle.solve().is_err() {
+            puzzle = unsolved;
+        }
+    }
+    let board = puzzle.get_board();
+
+    // counts
+    for (i, count) in puzzle.get_top_clues().iter().enumerate() {
+        let x = (i as f32 + 1.5) * CELL;
+        writeln!(svg, r#"<text x="{x}" y="7.5" font-size="7">{count}</text>"#).unwrap();
+    }
+    for (i, count) in puzzle.get_left_clues().iter().enumerate() {
+        let y = (i as f32 + 1.75) * CELL;
+        writeln!(svg, r#"<text x="5" y="{y}" font-size="7">{count}</text>"#).unwrap();
+    }
+
+    // cells
+    for (row, states) in board.iter().enumerate() {
+        for (col, state) in states.iter().enumerate() {
+            let x = (col as f32 + 1.0) * CELL;
+            let y = (row as f32 + 1.0) * CELL;
+            match state {
+                BoardState::Wall => writeln!(
+                    svg,
+                    r#"<rect x="{x}" y="{y}" width="{CELL}" height="{CELL}" fill="{WALL_FILL}"/>"#
+                )
+                .unwrap(),
+                BoardState::Enemy => draw_enemy(svg, x, y),
+                BoardState::Treasure => draw_treasure(svg, x, y),
+                BoardState::Path | BoardState::Empty => (),
+            }
+        }
+    }
+
+    // grid, with a heavier outline around the board
+    for i in 1..8 {
+        let offset = (i as f32 + 1.0) * CELL;
+        writeln!(
+            svg,
+            concat!(
+                r#"<line x1="{0}" y1="{1}" x2="{0}" y2="{2}" stroke="{3}" stroke-width="0.3"/>"#,
+                r#"<line x1="{1}" y1="{0}" x2="{2}" y2="{0}" stroke="{3}" stroke-width="0.3"/>"#
+            ),
+            offset,
+            CELL,
+            9.0 * CELL,
+            INK
+        )
+        .unwrap();
+    }
+    writeln!(
+        svg,
+        r#"<rect x="{CELL}" y="{CELL}" width="{0}" height="{0}" fill="none" stroke="{INK}" stroke-width="0.8"/>"#,
+        8.0 * CELL
+    )
+    .unwrap();
+
+    let caption = match puzzle.get_seed() {
+        Some(seed) => format!("Seed {seed:08}"),
+        None => "Unseeded".to_string(),
+    };
+    writeln!(
+        svg,
+        r#"<text x="{}" y="{}" font-size="5">{caption}</text>"#,
+        5.0 * CELL,
+        PUZZLE_HEIGHT - 1.5
+    )
+    .unwrap();
+}
+
+// a horned head with two eyes
+fn draw_enemy(svg: &mut String, x: f32, y: f32) {
+    let (cx, cy) = (x + CELL / 2.0, y + CELL / 2.0 + 0.5);
+    writeln!(
+        svg,
+        concat!(
+            r#"<path d="M{} {} L{} {} L{} {} Z M{} {} L{} {} L{} {} Z" fill="{}"/>"#,
+            r#"<circle cx="{}" cy="{}" r="3" fill="{}"/>"#,
+            r#"<circle cx="{}" cy="{}" r="0.7" fill="white"/>"#,
+            r#"<circle cx="{}" cy="{}" r="0.7" fill="white"/>"#
+        ),
+        cx - 2.8,
+        cy - 1.0,
+        cx - 3.2,
+        cy - 4.2,
+        cx - 1.2,
+        cy - 2.6,
+        cx + 2.8,
+        cy - 1.0,
+        cx + 3.2,
+        cy - 4.2,
+        cx + 1.2,
+        cy - 2.6,
+        INK,
+        cx,
+        cy,
+        INK,
+        cx - 1.2,
+        cy - 0.5,
+        cx + 1.2,
+        cy - 0.5
+    )
+    .unwrap();
+}
+
+// a chest with its lid and lock
+fn draw_treasure(svg: &mut String, x: f32, y: f32) {
+    writeln!(
+        svg,
+        concat!(
+            r#"<rect x="{}" y="{}" width="6" height="5" rx="0.6" fill="none" stroke="{}" stroke-width="0.6"/>"#,
+            r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="{}" stroke-width="0.6"/>"#,
+            r#"<rect x="{}" y="{}" width="1" height="1.4" fill="{}"/>"#
+        ),
+        x + 2.0,
+        y + 2.5,
+        INK,
+        x + 2.0,
+        y + 4.5,
+        x + 8.0,
+        y + 4.5,
+        INK,
+        x + 4.5,
+        y + 4.0,
+        INK
+    )
+    .unwrap();
+}
@@ -0,0 +1,581 @@
+//! Reads puzzles out of screenshots of the game. Nothing here depends on how the screenshot was
+//! taken, so saved captures such as `cropped.png` can be parsed on any platform.
+
+use std::{fmt::Display, sync::OnceLock};
+
+use image::{imageops, GrayImage, Rgba, RgbaImage};
+
+use crate::{
+    calibration::Calibration,
+    layout::{self, Layout, Region},
+    puzzle::{self, BoardState, Puzzle},
+    TileContents, IDS, NUM_BASE, NUM_SIZE,
+};
+
+// Size of the game window, everything is parsed relative to its top left corner
+pub const GAME_SIZE: (u32, u32) = (330, 458);
+
+// Sprites cut from the board of the game, each a whole tile. Golems, insects and minotaurs
+// haven't been captured yet, so they are never reported.
+#[rustfmt::skip]
+const SPRITES: [(TileContents, &[u8]); 15] = [
+    (TileContents::Chest, include_bytes!("../tiles/keep/Treasure.png")),
+    (TileContents::Skeleton, include_bytes!("../tiles/keep/Skeleton.png")),
+    (TileContents::SkeletonKing, include_bytes!("../tiles/keep/SkeletonKing.png")),
+    (TileContents::SkeletonWizard, include_bytes!("../tiles/keep/SkeletonWizard.png")),
+    (TileContents::Goblin, include_bytes!("../tiles/keep/Goblin.png")),
+    (TileContents::GoblinKing, include_bytes!("../tiles/keep/GoblinKing.png")),
+    (TileContents::Cthulu, include_bytes!("../tiles/keep/Cthulu.png")),
+    (TileContents::Eyes, include_bytes!("../tiles/keep/Eyes.png")),
+    (TileContents::Werewolf, include_bytes!("../tiles/keep/Werewolf.png")),
+    (TileContents::Goat, include_bytes!("../tiles/keep/Goat.png")),
+    (TileContents::Jelly, include_bytes!("../tiles/keep/Jelly.png")),
+    (TileContents::Demon, include_bytes!("../tiles/keep/Demon.png")),
+    (TileContents::DemonKing, include_bytes!("../tiles/keep/DemonKing.png")),
+    (TileContents::Druid, include_bytes!("../tiles/keep/Druid.png")),
+    (TileContents::Bear, include_bytes!("../tiles/keep/Bear.png")),
+];
+
+/// The sprite the game draws for a tile's contents, if it has been captured
+pub fn sprite(contents: TileContents) -> Option<&'static [u8]> {
+    SPRITES
+        .iter()
+        .find(|(sprite, _)| *sprite == contents)
+        .map(|(_, bytes)| *bytes)
+}
+
+// Every glyph the counts are drawn with. Counts only go up to 7, and a count of 0 is always
+// satisfied, so there is no red 0.
+#[rustfmt::skip]
+const DIGITS: [(u8, &[u8]); 15] = [
+    (0, include_bytes!("../nums/grey_0.png")),
+    (1, include_bytes!("../nums/grey_1.png")),
+    (2, include_bytes!("../nums/grey_2.png")),
+    (3, include_bytes!("../nums/grey_3.png")),
+    (4, include_bytes!("../nums/grey_4.png")),
+    (5, include_bytes!("../nums/grey_5.png")),
+    (6, include_bytes!("../nums/grey_6.png")),
+    (7, include_bytes!("../nums/grey_7.png")),
+    (1, include_bytes!("../nums/red_1.png")),
+    (2, include_bytes!("../nums/red_2.png")),
+    (3, include_bytes!("../nums/red_3.png")),
+    (4, include_bytes!("../nums/red_4.png")),
+    (5, include_bytes!("../nums/red_5.png")),
+    (6, include_bytes!("../nums/red_6.png")),
+    (7, include_bytes!("../nums/red_7.png")),
+];
+
+// A digit is only trusted when it correlates this well with its glyph, and clearly better than
+// with any other digit
+const MIN_DIGIT_SCORE: f32 = 0.6;
+const MIN_DIGIT_MARGIN: f32 = 0.1;
+
+// The seed digits, as extracted from captures of the seed box. Twos, threes and nines haven't
+// shown up in a capture yet, so they are drawn to match the rest and to agree with the pixels
+// the old run-length decoder told them apart by.
+#[rustfmt::skip]
+pub const SEED_GLYPHS: [[&str; 7]; 10] = [
+    ["..###..", ".#...#.", "##...##", "##.#.##", "##...##", ".#...#.", "..###.."],
+    [".###", "###.", ".##.", ".##.", ".##.", ".##.", "##.#"],
+    ["..###..", ".#...##", ".....##", "....##.", "..##...", ".##....", "#######"],
+    ["######.", "....#..", "...#...", "..####.", ".....##", "#....##", ".#####."],
+    ["...###.", "..#.##.", ".#..##.", "#######", "....##.", "....##.", "...##.#"],
+    ["#######", "##...#.", "##.....", "######.", ".....##", "#....##", ".#####."],
+    [".#####.", "##....#", "##.....", "######.", "##...##", "##...##", ".#####."],
+    [".######", ".#...##", "#...##.", "...#...", "..#....", ".##....", ".###..."],
+    [".#####.", "#....##", "##...##", ".##.##.", "##....#", "##...##", ".#####."],
+    [".#####.", "##...##", "##...##", ".######", ".....##", "#....##", ".#####."],
+];
+
+// Number of digits in the seed box, leading zeros included
+const SEED_DIGITS: usize = 8;
+
+// Colour of the frame of the seed box, which is only shown for seeded puzzles. Scaling and
+// colour correction shift it by a few levels.
+const SEED_FRAME: [u8; 3] = [83, 50, 61];
+const SEED_FRAME_TOLERANCE: u8 = 16;
+
+// Seed digits are dark brown on light stone. Pixels closer to the darkest one than to the
+// stone are part of a digit, unless the strip is too flat to hold any digits.
+const MIN_SEED_CONTRAST: f32 = 30.0;
+
+// A seed digit may differ from its glyph in this many pixels
+const MAX_SEED_MISMATCH: usize = 4;
+
+// A tile is only trusted when its best template is this close, and clearly closer than any
+// other. Hovering a tile brightens it by a few levels, well inside the limit.
+const MAX_TILE_DISTANCE: f32 = 20.0;
+const MIN_TILE_CONFIDENCE: f32 = 0.3;
+
+// The mouse cursor is drawn in pure black and white, which no sprite or floor uses under the
+// mask, so those pixels are left out. The arrow resting on a tile leaves about a tenth of the
+// mask's 93 pixels, still plenty to tell floor from sprites.
+const MIN_VISIBLE_PIXELS: usize = 8;
+
+// Pixels of a tile covered by every sprite, so the floor around them doesn't affect matching
+const SPRITE_MASK: &[u8] = include_bytes!("../mask.png");
+
+// A capture of an empty board, which gives the floor of each tile
+const EMPTY_BOARD: &[u8] = include_bytes!("../dungeon_empty.png");
+
+struct Templates {
+    mask: GrayImage,
+    sprites: Vec<(TileContents, RgbaImage)>,
+    floor: RgbaImage,
+    digits: Vec<(u8, Vec<f32>)>,
+}
+
+/// What a tile of the board holds, and how sure the match is
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TileMatch {
+    pub contents: TileContents,
+    /// From 0 to 1, how much closer the best template is than the runner-up. Exact matches
+    /// score 1, while anything below about 0.5 is worth a second look.
+    pub confidence: f32,
+    /// Mean difference per colour channel from the best template, 0 for an exact match
+    pub distance: f32,
+    /// Pixels of the mask compared, once the mouse cursor is left out
+    pub visible: usize,
+}
+
+impl TileMatch {
+    /// Whether the match is close and clear enough to be trusted
+    pub fn is_reliable(&self) -> bool {
+        self.distance <= MAX_TILE_DISTANCE
+            && self.confidence >= MIN_TILE_CONFIDENCE
+            && self.visible >= MIN_VISIBLE_PIXELS
+    }
+}
+
+/// The digit read from a count, and how far ahead of the next best digit it scored
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DigitMatch {
+    pub digit: u8,
+    pub margin: f32,
+}
+
+#[derive(Debug)]
+pub enum PatternSearchError {
+    NotFound,
+    MultipleResults(usize),
+    OutOfBounds,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OcrError {
+    /// No glyph matched the count well enough. `best` is the closest digit, with its
+    /// correlation and its lead over the runner-up.
+    UnreadableDigit {
+        best: u8,
+        score: f32,
+        margin: f32,
+    },
+    /// The tile at x,y is too far from every template, or too close to two of them
+    UnreadableTile {
+        x: usize,
+        y: usize,
+        best: TileContents,
+        confidence: f32,
+    },
+    /// The digit at this position of the seed matches no glyph
+    UnreadableSeed(usize),
+    /// The seed box held this many digits instead of eight
+    SeedLength(usize),
+    SeedOutOfRange(u64),
+}
+
+impl Display for OcrError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OcrError::UnreadableDigit {
+                best,
+                score,
+                margin,
+            } => write!(
+                f,
+                "unreadable count, closest to {best} (score {score:.2}, margin {margin:.2})"
+            ),
+            OcrError::UnreadableTile {
+                x,
+                y,
+                best,
+                confidence,
+            } => write!(
+                f,
+                "unreadable tile at {x},{y}, closest to {best:?} (confidence {confidence:.2})"
+            ),
+            OcrError::UnreadableSeed(i) => write!(f, "unreadable digit {i} of the seed"),
+            OcrError::SeedLength(n) => write!(f, "read {n} seed digits instead of {SEED_DIGITS}"),
+            OcrError::SeedOutOfRange(seed) => write!(f, "seed {seed} is out of range"),
+        }
+    }
+}
+
+impl std::error::Error for OcrError {}
+
+/// Anything that stops a puzzle from being read out of a screenshot
+#[derive(Debug)]
+pub enum VisionError {
+    Search(PatternSearchError),
+    Ocr(OcrError),
+}
+
+impl From<PatternSearchError> for VisionError {
+    fn from(e: PatternSearchError) -> Self {
+        VisionError::Search(e)
+    }
+}
+
+impl From<OcrError> for VisionError {
+    fn from(e: OcrError) -> Self {
+        VisionError::Ocr(e)
+    }
+}
+
+/// Finds the game in a screenshot and reads the puzzle on it. The screenshot can either be of
+/// the whole window or already cropped to the game, at any scale.
+pub fn parse_puzzle_from_image(
+    image: &RgbaImage,
+    calibration: &Calibration,
+) -> Result<Puzzle, VisionError> {
+    let layout = layout::find_layout(image)?;
+    Ok(parse_game(&layout.crop(image), calibration)?)
+}
+
+/// Reads the puzzle from an image cropped to the game window at 1x, as made by `Layout::crop`
+pub fn parse_game(game: &RgbaImage, calibration: &Calibration) -> Result<Puzzle, OcrError> {
+    Ok(puzzle::new(
+        parse_top_nums(game, calibration)?,
+        parse_left_nums(game, calibration)?,
+        parse_board(game, calibration)?,
+        parse_seed(game, calibration)?,
+    ))
+}
+
+pub fn parse_top_nums(game: &RgbaImage, calibration: &Calibration) -> Result<[u8; 8], OcrError> {
+    let layout = Layout::unscaled(*calibration);
+    let mut nums = [0; 8];
+    for (i, num) in nums.iter_mut().enumerate() {
+        let tile = sub_image(game, layout.top_num(i));
+        *num = parse_digit(&tile)?.digit;
+    }
+    Ok(nums)
+}
+
+pub fn parse_left_nums(game: &RgbaImage, calibration: &Calibration) -> Result<[u8; 8], OcrError> {
+    let layout = Layout::unscaled(*calibration);
+    let mut nums = [0; 8];
+    for (i, num) in nums.iter_mut().enumerate() {
+        let tile = sub_image(game, layout.left_num(i));
+        *num = parse_digit(&tile)?.digit;
+    }
+    Ok(nums)
+}
+
+/// The board as the solver sees it, failing on the first tile that can't be told apart
+pub fn parse_board(
+    game: &RgbaImage,
+    calibration: &Calibration,
+) -> Result<[[BoardState; 8]; 8], OcrError> {
+    let tiles = parse_tiles(game, calibration);
+    for (y, row) in tiles.iter().enumerate() {
+        for (x, tile) in row.iter().enumerate() {
+            if !tile.is_reliable() {
+                return Err(OcrError::UnreadableTile {
+                    x,
+                    y,
+                    best: tile.contents,
+                    confidence: tile.confidence,
+                });
+            }
+        }
+    }
+    Ok(tiles.map(|row| row.map(|tile| tile.contents.board_state())))
+}
+
+/// Identifies the monster, chest or empty floor on every tile of the board
+pub fn parse_tiles(game: &RgbaImage, calibration: &Calibration) -> [[TileMatch; 8]; 8] {
+    IDS.map(|row| IDS.map(|col| classify_tile(game, calibration, col as usize, row as usize)))
+}
+
+/// Matches the tile at col,row against every known sprite and the empty floor, comparing only
+/// the pixels under the sprite mask that the mouse cursor leaves visible
+pub fn classify_tile(
+    game: &RgbaImage,
+    calibration: &Calibration,
+    col: usize,
+    row: usize,
+) -> TileMatch {
+    let templates = templates();
+    let (x, y, _, _) = Layout::unscaled(*calibration).tile(col, row);
+    // the floor is drawn at the same place in the empty board it was captured from
+    let (fx, fy, _, _) = Layout::unscaled(Calibration::DEFAULT).tile(col, row);
+
+    // the masked pixels the cursor isn't drawn over
+    let visible = templates
+        .mask
+        .enumerate_pixels()
+        .filter(|(_, _, m)| m.0[0] != 0)
+        .map(|(mx, my, _)| (mx, my))
+        .filter(|&(mx, my)| {
+            let p = game.get_pixel(x + mx, y + my).0;
+            p[..3] != [0, 0, 0] && p[..3] != [255, 255, 255]
+        })
+        .collect::<Vec<(u32, u32)>>();
+
+    let distance = |template: &RgbaImage, tx: u32, ty: u32| {
+        if visible.is_empty() {
+            return f32::INFINITY;
+        }
+        let mut total = 0u32;
+        for &(mx, my) in visible.iter() {
+            let a = game.get_pixel(x + mx, y + my).0;
+            let b = template.get_pixel(tx + mx, ty + my).0;
+            total += (0..3).map(|c| a[c].abs_diff(b[c]) as u32).sum::<u32>();
+        }
+        total as f32 / (3 * visible.len()) as f32
+    };
+
+    let mut scores = templates
+        .sprites
+        .iter()
+        .map(|(contents, sprite)| (*contents, distance(sprite, 0, 0)))
+        .collect::<Vec<(TileContents, f32)>>();
+    scores.push((TileContents::Empty, distance(&templates.floor, fx, fy)));
+    scores.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    let (contents, best) = scores[0];
+    let runner_up = scores[1].1;
+    let confidence = if runner_up == 0.0 || runner_up.is_infinite() {
+        0.0
+    } else {
+        (runner_up - best) / runner_up
+    };
+    TileMatch {
+        contents,
+        confidence,
+        distance: best,
+        visible: visible.len(),
+    }
+}
+
+// the templates are decoded once and shared by every parse
+fn templates() -> &'static Templates {
+    static TEMPLATES: OnceLock<Templates> = OnceLock::new();
+    TEMPLATES.get_or_init(|| {
+        let load = |bytes: &[u8]| {
+            image::load_from_memory(bytes)
+                .expect("Bundled image is invalid")
+                .to_rgba8()
+        };
+        Templates {
+            mask: image::load_from_memory(SPRITE_MASK)
+                .expect("Bundled image is invalid")
+                .to_luma8(),
+            sprites: SPRITES
+                .iter()
+                .map(|(contents, bytes)| (*contents, load(bytes)))
+                .collect(),
+            floor: load(EMPTY_BOARD),
+            digits: DIGITS
+                .iter()
+                .map(|(digit, bytes)| (*digit, normalize(&load(bytes))))
+                .collect(),
+        }
+    })
+}
+
+/// Reads the seed of a seeded puzzle, or None for a random one. The seed box is split into
+/// digits at the columns without ink, and each digit is matched against `SEED_GLYPHS`.
+pub fn parse_seed(game: &RgbaImage, calibration: &Calibration) -> Result<Option<u32>, OcrError> {
+    // check if we are in seeded
+    let (sx, sy) = calibration.sample_point_seed;
+    let frame = game.get_pixel(sx as u32, sy as u32);
+    let seeded = (0..3).all(|c| frame[c].abs_diff(SEED_FRAME[c]) <= SEED_FRAME_TOLERANCE);
+
+    if !seeded {
+        return Ok(None);
+    }
+
+    let (bx, by, width, height) = Layout::unscaled(*calibration).seed();
+    let mut strip = (bx..bx + width)
+        .flat_map(|x| (by..by + height).map(move |y| luma(game.get_pixel(x, y))))
+        .collect::<Vec<f32>>();
+    strip.sort_by(f32::total_cmp);
+    let (darkest, stone) = (strip[0], strip[strip.len() / 2]);
+    // an empty seed box is left by puzzles that were never given a seed, such as renders
+    if stone - darkest < MIN_SEED_CONTRAST {
+        return Ok(None);
+    }
+    let threshold = (darkest + stone) / 2.0;
+    let ink = |x: u32, y: u32| luma(game.get_pixel(x, y)) < threshold;
+    let column = |x: u32| (by..by + height).map(|y| ink(x, y)).collect::<Vec<bool>>();
+
+    let mut seed = 0u64;
+    let mut digit = vec![];
+    let mut position = 0;
+    for x in bx..=bx + width {
+        let pixels = column(x);
+        if x < bx + width && pixels.contains(&true) {
+            digit.push(pixels);
+        } else if !digit.is_empty() {
+            let value = match_seed_digit(&digit).ok_or(OcrError::UnreadableSeed(position))?;
+            seed = seed.saturating_mul(10).saturating_add(value);
+            position += 1;
+            digit.clear();
+        }
+    }
+
+    if position != SEED_DIGITS {
+        return Err(OcrError::SeedLength(position));
+    }
+    if seed >= puzzle::SEED_MAX as u64 {
+        return Err(OcrError::SeedOutOfRange(seed));
+    }
+    Ok(Some(seed as u32))
+}
+
+// the glyph closest to the columns of a digit, if it is close enough
+fn match_seed_digit(columns: &[Vec<bool>]) -> Option<u64> {
+    let mismatches = |glyph: &[&str; 7]| {
+        let width = columns.len().max(glyph[0].len());
+        (0..width)
+            .flat_map(|x| (0..7).map(move |y| (x, y)))
+            .filter(|&(x, y)| {
+                let seen = columns.get(x).is_some_and(|column| column[y]);
+                let expected = glyph[y].as_bytes().get(x) == Some(&b'#');
+                seen != expected
+            })
+            .count()
+    };
+
+    let (digit, best) = SEED_GLYPHS
+        .iter()
+        .map(mismatches)
+        .enumerate()
+        .min_by_key(|&(_, count)| count)?;
+    (best <= MAX_SEED_MISMATCH).then_some(digit as u64)
+}
+
+fn sub_image(image: &RgbaImage, (x, y, width, height): Region) -> RgbaImage {
+    assert!(x + width < image.width());
+    assert!(y + height < image.height());
+    imageops::crop_imm(image, x, y, width, height).to_image()
+}
+
+/// Reads a count by correlating the tile with every red and grey glyph. Correlation ignores
+/// brightness and contrast, so gamma changes and the grey of a satisfied count don't matter.
+pub fn parse_digit(tile: &RgbaImage) -> Result<DigitMatch, OcrError> {
+    let tile = normalize(tile);
+    let mut scores = [f32::MIN; 8];
+    for (digit, glyph) in templates().digits.iter() {
+        let score = tile.iter().zip(glyph).map(|(a, b)| a * b).sum::<f32>();
+        scores[*digit as usize] = scores[*digit as usize].max(score);
+    }
+
+    let mut ranked = (0..8u8).collect::<Vec<u8>>();
+    ranked.sort_by(|&a, &b| scores[b as usize].total_cmp(&scores[a as usize]));
+    let best = ranked[0];
+    let score = scores[best as usize];
+    let margin = score - scores[ranked[1] as usize];
+
+    if score < MIN_DIGIT_SCORE || margin < MIN_DIGIT_MARGIN {
+        Err(OcrError::UnreadableDigit {
+            best,
+            score,
+            margin,
+        })
+    } else {
+        Ok(DigitMatch {
+            digit: best,
+            margin,
+        })
+    }
+}
+
+// luma of every pixel, shifted to a mean of zero and scaled to unit length so that a dot product
+// of two tiles is their correlation
+fn normalize(tile: &RgbaImage) -> Vec<f32> {
+    let glyph = imageops::crop_imm(
+        tile,
+        NUM_BASE.0 as u32,
+        NUM_BASE.1 as u32,
+        NUM_SIZE.0 as u32,
+        NUM_SIZE.1 as u32,
+    )
+    .to_image();
+    let luma = glyph.pixels().map(luma).collect::<Vec<f32>>();
+    let mean = luma.iter().sum::<f32>() / luma.len() as f32;
+    let centered = luma.iter().map(|l| l - mean).collect::<Vec<f32>>();
+    let length = centered.iter().map(|l| l * l).sum::<f32>().sqrt();
+    if length == 0.0 {
+        centered
+    } else {
+        centered.iter().map(|l| l / length).collect()
+    }
+}
+
+fn luma(p: &Rgba<u8>) -> f32 {
+    0.299 * p.0[0] as f32 + 0.587 * p.0[1] as f32 + 0.114 * p.0[2] as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the pixels the original run-length decoder told seed digits apart by: the length of the
+    // first run of ink along the top row, then a pixel or two below it
+    fn run_length_digit(glyph: &[&str; 7]) -> u8 {
+        let ink = |x: usize, y: usize| glyph[y].as_bytes().get(x) == Some(&b'#');
+        let start = glyph[0].find('#').unwrap();
+        let run = glyph[0][start..].bytes().take_while(|&p| p == b'#').count();
+        match run {
+            3 if ink(start + 1, 1) => {
+                if ink(start, 1) {
+                    1
+                } else {
+                    4
+                }
+            }
+            3 if ink(start + 1, 3) => 0,
+            3 => 2,
+            5 if ink(start, 1) => {
+                if ink(start + 4, 1) {
+                    9
+                } else {
+                    6
+                }
+            }
+            5 => 8,
+            6 if ink(start + 5, 1) => 7,
+            6 => 3,
+            7 => 5,
+            n => panic!("no digit starts with a run of {n}"),
+        }
+    }
+
+    fn columns(glyph: &[&str; 7]) -> Vec<Vec<bool>> {
+        (0..glyph[0].len())
+            .map(|x| {
+                glyph
+                    .iter()
+                    .map(|line| line.as_bytes()[x] == b'#')
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn seed_glyphs_agree_with_run_length_decoder() {
+        for (digit, glyph) in SEED_GLYPHS.iter().enumerate() {
+            assert_eq!(run_length_digit(glyph) as usize, digit);
+        }
+    }
+
+    #[test]
+    fn seed_glyphs_match_themselves() {
+        for (digit, glyph) in SEED_GLYPHS.iter().enumerate() {
+            assert_eq!(match_seed_digit(&columns(glyph)), Some(digit as u64));
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use std::path::Path;
+
+use dungeons_n_diagrams::{
+    calibration::Calibration,
+    fixtures::{check_fixtures, FIXTURES_DIR},
+};
+
+#[test]
+fn every_fixture_reads_back() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(FIXTURES_DIR);
+    let results = check_fixtures(&dir, &Calibration::DEFAULT).unwrap();
+
+    assert!(!results.is_empty(), "no captures in {}", dir.display());
+    let failures = results
+        .iter()
+        .filter(|result| !result.passed())
+        .map(|result| result.to_string())
+        .collect::<Vec<_>>();
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
@@ -0,0 +1,59 @@
+use std::path::Path;
+
+use dungeons_n_diagrams::{
+    backend::{MockBackend, MouseButton},
+    calibration::Calibration,
+    fixtures::FIXTURES_DIR,
+    layout::find_layout,
+    puzzle::{BoardState, Puzzle},
+    DungeonCrawler, TILE_SIZE,
+};
+
+const WINDOW_POS: (i32, i32) = (100, 50);
+
+#[test]
+fn solve_loop_places_the_solution() {
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join(FIXTURES_DIR);
+    let capture = fixtures.join("seeded_enemies_treasures.png");
+    let mut solution = std::fs::read_to_string(capture.with_extension("txt"))
+        .unwrap()
+        .parse::<Puzzle>()
+        .unwrap();
+    solution.solve().unwrap();
+
+    // one frame is taken to find the game, one to read the puzzle, and the next capture fails
+    let backend = MockBackend::from_files(&[&capture, &capture])
+        .unwrap()
+        .with_window_pos(WINDOW_POS.0, WINDOW_POS.1);
+    let mut crawler = DungeonCrawler::new(backend).unwrap();
+    crawler.solve_loop();
+
+    let layout = find_layout(&image::open(&capture).unwrap().to_rgba8()).unwrap();
+    let base = Calibration::DEFAULT.board_base;
+    let tile = |x: i32, y: i32| {
+        let x = (x - WINDOW_POS.0 - layout.origin.0 as i32) / layout.scale as i32 - base.0 as i32;
+        let y = (y - WINDOW_POS.1 - layout.origin.1 as i32) / layout.scale as i32 - base.1 as i32;
+        let size = (8 * TILE_SIZE) as i32;
+        ((0..size).contains(&x) && (0..size).contains(&y))
+            .then_some((x as usize / TILE_SIZE, y as usize / TILE_SIZE))
+    };
+
+    let board = solution.get_board();
+    let mut walls = vec![];
+    for &(x, y, button) in &crawler.backend().clicks {
+        let Some((tx, ty)) = tile(x, y) else {
+            continue;
+        };
+        match button {
+            MouseButton::Left => walls.push((tx, ty)),
+            MouseButton::Right => assert_ne!(board[ty][tx], BoardState::Wall, "path at {tx},{ty}"),
+        }
+    }
+    walls.sort();
+
+    let expected = (0..8)
+        .flat_map(|x| (0..8).map(move |y| (x, y)))
+        .filter(|&(x, y)| board[y][x] == BoardState::Wall)
+        .collect::<Vec<_>>();
+    assert_eq!(walls, expected);
+}
@@ -0,0 +1,27 @@
+#![cfg(feature = "x11")]
+use std::{path::Path, thread, time::Duration};
+
+use dungeons_n_diagrams::{
+    backend::{GameBackend, X11Backend},
+    fixtures::FIXTURES_DIR,
+    WINDOW_NAME,
+};
+
+#[test]
+#[ignore = "needs an X server, such as `Xvfb :99` with DISPLAY=:99"]
+fn captures_a_stand_in_window() {
+    let capture = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join(FIXTURES_DIR)
+        .join("seeded_empty_board.png");
+    let image = image::open(capture).unwrap().to_rgba8();
+
+    let mut backend = X11Backend::new().unwrap();
+    backend.open_stand_in(WINDOW_NAME, &image).unwrap();
+    // the server paints the background once it has handled the map
+    thread::sleep(Duration::from_millis(100));
+
+    backend.find_window(WINDOW_NAME).unwrap();
+    let captured = backend.capture().unwrap();
+    assert_eq!(captured.dimensions(), image.dimensions());
+    assert!(captured == image, "the capture differs from the stand-in");
+}